@@ -1,91 +1,87 @@
+use crate::utils::token_request::{
+    authenticate_client, parse_token_request, validate_grant, TokenError,
+};
+use crate::utils::tokens::{issue_token, TokenResponse};
 use crate::AppState;
-use axum::extract::multipart::Multipart;
-use axum::extract::State;
-use axum::http::{HeaderMap, StatusCode};
+use axum::extract::{Request, State};
+use axum::http::header::{CACHE_CONTROL, PRAGMA};
+use axum::http::{HeaderMap, HeaderName};
+use axum::Json;
 use log::debug;
 use std::sync::Arc;
 
-/// Dummy handler for the auth flow
+/// Handler for the oauth2 client credentials flow
 ///
-/// Generally speaking the auth flow for a VTN should follow the standard oauth2 flow. For simplicity in the test tool,
-/// we're just going to be returning static fake token defined in Secrets. The test tool should not be used to store
-/// sensitive data.
+/// Authenticates the client against the client registry and issues a random access token with a limited lifetime,
+/// which is tracked in memory with the client identity until it expires. With the `STATIC_TOKEN = "true"` compatibility
+/// switch the static `DUMMY_TOKEN` secret is issued instead. The test tool should not be used to store sensitive data.
+///
+/// Like RFC 6749 section 5.1 requires, the token response is sent with `Cache-Control: no-store` and
+/// `Pragma: no-cache` headers.
 ///
 /// # Parameters
 /// - `state`: The shared memory state of the application
 /// - `headers`: The headers of the request
-/// - `body`: The body of the request, should be multipart form data with grant_type and scope fields
+/// - `request`: The request, the body should be urlencoded, multipart form data or JSON with grant_type and scope
+///   fields, and optionally client_id and client_secret fields instead of the Basic auth header
 ///
 /// # Returns
-/// - `Result<String, (StatusCode, String)>`: The token if the auth is successful, otherwise an error
+/// - `Result<([(HeaderName, &'static str); 2], Json<TokenResponse>), TokenError>`: The token response with the cache
+///   headers if the auth is successful, otherwise an RFC 6749 error response
 pub async fn post_auth(
     state: State<Arc<AppState>>,
     headers: HeaderMap,
-    mut body: Multipart,
-) -> Result<String, (StatusCode, String)> {
-    // Fetch secrets
-    let secrets = &state.secrets;
+    request: Request,
+) -> Result<([(HeaderName, &'static str); 2], Json<TokenResponse>), TokenError> {
+    let body = parse_token_request(request).await?;
 
-    // Extract auth headers and validate them
-    let valid_header = secrets
-        .get("BASIC_AUTH_HEADER")
-        .expect("BASIC_AUTH_HEADER not set!"); // Panic if secret not set
-    let auth_header = headers.get("Authorization");
-    match auth_header {
-        Some(header) => {
-            let header = header.to_str().unwrap();
-            if header != valid_header {
-                debug!("Invalid auth header: {}", header);
-                return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-            }
-        }
-        None => {
-            debug!("No auth header found");
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                "No authorization header".to_string(),
-            ));
-        }
-    }
+    // Validate the client credentials from either the auth header or the body
+    let client = authenticate_client(&state.clients, &headers, &body)?;
+    debug!(
+        "Client credentials validated for client: {}",
+        client.client_id
+    );
+
+    // grant type should be client_credentials, scope has to be allowed for the client and is returned with the token
+    let scope = validate_grant(&body, &client)?;
 
-    debug!("Auth header header validated");
+    let token = issue_token(&state, &client, Some(scope));
+    Ok((
+        [(CACHE_CONTROL, "no-store"), (PRAGMA, "no-cache")],
+        Json(token),
+    ))
+}
 
-    // Extract the form data and "generate" a token
-    // the form data contents should be grant_type, and scope. grant type should be client_credentials,
-    // scope we don't really care about here for testing purposes
-    let mut found_grant_type = false;
-    let mut found_scope = false;
-    while let Some(field) = body.next_field().await.unwrap() {
-        if field.name().is_some_and(|name| name == "grant_type") {
-            let value = field.text().await.unwrap();
-            if value != "client_credentials" {
-                debug!("Invalid grant_type: {}", value);
-                return Err((StatusCode::BAD_REQUEST, "Invalid grant_type".to_string()));
-            }
-            debug!("Grant type found: {}", value);
-            found_grant_type = true;
-            continue;
-        }
-        if field.name().is_some_and(|name| name == "scope") {
-            let value = field.text().await.unwrap();
-            debug!("Scope found: {}", value);
-            found_scope = true;
-            continue;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_helpers::test_state;
+    use axum::body::Body;
+    use axum::http::header::CONTENT_TYPE;
+    use axum::response::IntoResponse;
 
-        // Auth should contain these 2 fields and only these 2 fields, if anything else is found, return an error
-        debug!("Invalid field found: {:?}", field.name());
-        return Err((StatusCode::BAD_REQUEST, "Invalid form data".to_string()));
-    }
+    #[tokio::test]
+    async fn test_post_auth_cache_headers() {
+        let state = test_state(&[(
+            "OAUTH_CLIENTS",
+            r#"[{"clientId": "ven1", "clientSecret": "secret"}]"#,
+        )])
+        .await;
+        let request = Request::builder()
+            .method("POST")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(
+                "grant_type=client_credentials&scope=read_all&client_id=ven1&client_secret=secret",
+            ))
+            .unwrap();
+        let headers = request.headers().clone();
 
-    if !found_grant_type || !found_scope {
-        debug!("Missing grant_type or scope");
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Missing grant_type or scope".to_string(),
-        ));
+        let response = post_auth(State(state), headers, request)
+            .await
+            .unwrap()
+            .into_response();
+        assert!(response.status().is_success());
+        assert_eq!(response.headers().get(CACHE_CONTROL).unwrap(), "no-store");
+        assert_eq!(response.headers().get(PRAGMA).unwrap(), "no-cache");
     }
-
-    let token = secrets.get("DUMMY_TOKEN").expect("DUMMY_TOKEN not set");
-    Ok(token)
 }
@@ -1,3 +1,6 @@
+use crate::utils::notifications::notify_event_subscribers;
+use crate::utils::openadr_models::{ObjectTypes, Operation};
+use crate::utils::sessions::session_state;
 use crate::AppState;
 use axum::extract::State;
 use axum::http::{HeaderMap, StatusCode};
@@ -6,7 +9,7 @@ use std::sync::Arc;
 /// Clear the list of events stored in the test VTN
 ///
 /// Can be used to test the /events endpoint with an empty list of events and ensure poller handles empty
-/// event lists gracefully
+/// event lists gracefully. Subscriptions to event deletion are notified of each removed event.
 ///
 /// # Parameters
 /// - `shared_memory`: The shared memory state of the application
@@ -18,15 +21,20 @@ pub async fn post_clear_events(
     shared_memory: State<Arc<AppState>>,
     header_map: HeaderMap,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    // auth
-    let auth_valid = crate::utils::authorizer::authorizer(&shared_memory.secrets, header_map).await;
-    if !auth_valid {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-    }
+    // Test session
+    let shared_memory = session_state(&shared_memory, &header_map)?;
+
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&shared_memory, header_map).await?;
 
     // Clear the events list
     let mut storage = shared_memory.event_storage.write().await;
-    storage.clear();
+    for event in storage.drain(..) {
+        notify_event_subscribers(&shared_memory, Operation::DELETE, &event);
+    }
+    shared_memory
+        .owners
+        .retain(|(object_type, _id), _owner| *object_type != ObjectTypes::EVENT);
 
     Ok(StatusCode::OK)
 }
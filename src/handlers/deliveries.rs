@@ -0,0 +1,77 @@
+use crate::utils::sessions::session_state;
+use crate::utils::webhook_delivery::DeliveryRecord;
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use log::debug;
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Query parameters for filtering the delivery log
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQuery {
+    /// Only return deliveries made for this subscription
+    #[serde(rename = "subscriptionID")]
+    pub subscription_id: Option<String>,
+}
+
+/// Get the webhook delivery log
+///
+/// Returns a record of every delivery made to a subscription callback URL, including the payload, response status,
+/// response body, latency and number of attempts. Automated tests can use this to assert that pushes actually
+/// reached the VEN.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `query`: Optional `subscriptionID` query parameter to filter the deliveries
+///
+/// # Returns
+/// - `Result<Json<Vec<DeliveryRecord>>, (StatusCode, String)>`: The delivery records, or an error if the request failed
+pub async fn get_deliveries(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    query: Query<DeliveryQuery>,
+) -> Result<Json<Vec<DeliveryRecord>>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&state, header_map).await?;
+
+    let delivery_log = state.delivery_log.read().await;
+    let deliveries: Vec<DeliveryRecord> = delivery_log
+        .iter()
+        .filter(|record| {
+            query.subscription_id.is_none() || record.subscription_id == query.subscription_id
+        })
+        .cloned()
+        .collect();
+
+    debug!("Returning {} delivery records", deliveries.len());
+    Ok(Json(deliveries))
+}
+
+/// Clear the webhook delivery log
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+///
+/// # Returns
+/// - `Result<StatusCode, (StatusCode, String)>`: The status code of the request, or an error if the request failed
+pub async fn delete_deliveries(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&state, header_map).await?;
+
+    state.delivery_log.write().await.clear();
+
+    Ok(StatusCode::OK)
+}
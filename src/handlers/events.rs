@@ -1,7 +1,14 @@
-use crate::utils::authorizer::authorizer;
-use crate::utils::openadr_models::OpenADREvent;
+use crate::utils::authorizer::authorize_scope;
+use crate::utils::client_access::{can_modify, event_visible, remove_owner, set_owner};
+use crate::utils::iso8601::Timestamp;
+use crate::utils::notifications::notify_event_subscribers;
+use crate::utils::openadr_models::{ObjectTypes, OpenADREvent, Operation};
+use crate::utils::query_filters::QueryFilters;
+use crate::utils::resource_validation::{resource_names, validate_resource_names};
+use crate::utils::sessions::session_state;
+use crate::utils::tokens::Scope;
 use crate::AppState;
-use axum::extract::State;
+use axum::extract::{Path, Query, State};
 use axum::http::{HeaderMap, StatusCode};
 use axum::Json;
 use log::debug;
@@ -13,29 +20,409 @@ use std::sync::Arc;
 /// mimicking a GET Events call to the VTN server. If new events have been generated using the generate_event handler, the new generated events
 /// will also be returned here.
 ///
+/// The OpenADR 3.0 query parameters `programID`, `targetType`, `targetValues`, `skip` and `limit` are supported
+/// to filter and page the returned events. RESOURCE_NAME target values are validated against the registered
+/// resources according to the unregistered resource policy.
+///
+/// Clients without the read_all scope only get the events they created, events without targets and events targeted
+/// to the VENs they registered.
+///
 /// # Parameters
 /// - `headers`: The headers of the request
 /// - `shared_memory`: The shared memory state of the application
+/// - `query`: The query string key value pairs of the request
 ///
 /// # Returns
-/// - `Result<Json<Vec<OpenADREvent>>, (StatusCode, String)>`: The OpenADR event array if the auth is successful, otherwise an error
+/// - `Result<(HeaderMap, Json<Vec<OpenADREvent>>), (StatusCode, String)>`: The OpenADR event array if the auth is successful, otherwise an error
 pub async fn get_events(
     headers: HeaderMap,
     shared_memory: State<Arc<AppState>>,
-) -> Result<Json<Vec<OpenADREvent>>, (StatusCode, String)> {
+    query: Query<Vec<(String, String)>>,
+) -> Result<(HeaderMap, Json<Vec<OpenADREvent>>), (StatusCode, String)> {
+    // Test session
+    let shared_memory = session_state(&shared_memory, &headers)?;
+
     // auth
-    let valid = authorizer(&shared_memory.secrets, headers).await;
-    if !valid {
-        debug!("Invalid auth header");
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-    }
+    let token = authorize_scope(&shared_memory, headers, Scope::ReadTargets).await?;
+
+    let filters = QueryFilters::from_pairs(query.0).map_err(|err| {
+        debug!("Invalid query parameters: {}", err);
+        (StatusCode::BAD_REQUEST, err)
+    })?;
+    let response_headers = match filters.target_type.as_deref() {
+        Some("RESOURCE_NAME") => validate_resource_names(&shared_memory, &filters.target_values)?,
+        _ => HeaderMap::new(),
+    };
 
-    // Get the event storage
+    // Get the event storage and filter the events according to the query parameters
     let storage = shared_memory.event_storage.read().await;
-    let events = storage.clone();
+    let events: Vec<OpenADREvent> = storage
+        .iter()
+        .filter(|event| {
+            event_visible(&shared_memory, &token, event)
+                && filters.matches_program(&event.program_id)
+                && filters.matches_targets(&event.targets)
+        })
+        .cloned()
+        .collect();
+    let events = filters.paginate(events);
+
+    debug!("Returning events: {:?}", events);
 
-    debug!("Returning dummy event: {:?}", events);
+    Ok((response_headers, Json(events)))
+}
 
-    // Here's where we'd manipulate the event object, but for now we'll just return it as is
-    Ok(Json(events))
+/// Handler for fetching a single event
+///
+/// # Parameters
+/// - `headers`: The headers of the request
+/// - `shared_memory`: The shared memory state of the application
+/// - `event_id`: The ID of the event to get as a path parameter
+///
+/// # Returns
+/// - `Result<Json<OpenADREvent>, (StatusCode, String)>`: The OpenADR event, or an error if the request failed
+pub async fn get_event(
+    headers: HeaderMap,
+    shared_memory: State<Arc<AppState>>,
+    event_id: Path<String>,
+) -> Result<Json<OpenADREvent>, (StatusCode, String)> {
+    // Test session
+    let shared_memory = session_state(&shared_memory, &headers)?;
+
+    // auth
+    let token = authorize_scope(&shared_memory, headers, Scope::ReadTargets).await?;
+
+    let storage = shared_memory.event_storage.read().await;
+    match storage.iter().find(|event| {
+        event.id.as_ref() == Some(&event_id.0) && event_visible(&shared_memory, &token, event)
+    }) {
+        Some(event) => Ok(Json(event.clone())),
+        None => Err((StatusCode::NOT_FOUND, "Event not found".to_string())),
+    }
+}
+
+/// Handler for creating an event
+///
+/// The VTN provisions the ID, creation and modification timestamps of the event. Any values for these fields
+/// in the request body are ignored. The created event will be returned by the GET events endpoints and
+/// subscriptions to event creation are notified. RESOURCE_NAME targets are validated against the registered
+/// resources according to the unregistered resource policy.
+///
+/// # Parameters
+/// - `headers`: The headers of the request
+/// - `shared_memory`: The shared memory state of the application
+/// - `event`: The event object to create
+///
+/// # Returns
+/// - `Result<(StatusCode, HeaderMap, Json<OpenADREvent>), (StatusCode, String)>`: The created event, or an error if the request failed
+pub async fn post_event(
+    headers: HeaderMap,
+    shared_memory: State<Arc<AppState>>,
+    event: Json<OpenADREvent>,
+) -> Result<(StatusCode, HeaderMap, Json<OpenADREvent>), (StatusCode, String)> {
+    // Test session
+    let shared_memory = session_state(&shared_memory, &headers)?;
+
+    // auth
+    let token = authorize_scope(&shared_memory, headers, Scope::WriteEvents).await?;
+
+    let mut event = event.0;
+    let response_headers =
+        validate_resource_names(&shared_memory, &resource_names(&event.targets))?;
+
+    // Provision the VTN controlled fields
+    let time_now = Timestamp::now();
+    event.id = Some(uuid::Uuid::new_v4().to_string());
+    event.created_date_time = Some(time_now);
+    event.modification_date_time = Some(time_now);
+    event.object_type = Some(ObjectTypes::EVENT);
+
+    let mut storage = shared_memory.event_storage.write().await;
+    storage.push(event.clone());
+    set_owner(
+        &shared_memory,
+        ObjectTypes::EVENT,
+        event.id.as_ref().unwrap(),
+        &token,
+    );
+
+    debug!("Event created: {:?}", event);
+    notify_event_subscribers(&shared_memory, Operation::POST, &event);
+    Ok((StatusCode::CREATED, response_headers, Json(event)))
+}
+
+/// Handler for updating an existing event
+///
+/// The ID and creation timestamp of the stored event are kept, the modification timestamp is updated.
+/// Subscriptions to event updates are notified. RESOURCE_NAME targets are validated like when creating an event.
+/// Events created by other clients or the admin endpoints can only be updated with the read_all scope, events the
+/// client can't see aren't found.
+///
+/// # Parameters
+/// - `headers`: The headers of the request
+/// - `shared_memory`: The shared memory state of the application
+/// - `event_id`: The ID of the event to update as a path parameter
+/// - `event`: The updated event object
+///
+/// # Returns
+/// - `Result<(HeaderMap, Json<OpenADREvent>), (StatusCode, String)>`: The updated event, or an error if the request failed
+pub async fn put_event(
+    headers: HeaderMap,
+    shared_memory: State<Arc<AppState>>,
+    event_id: Path<String>,
+    event: Json<OpenADREvent>,
+) -> Result<(HeaderMap, Json<OpenADREvent>), (StatusCode, String)> {
+    // Test session
+    let shared_memory = session_state(&shared_memory, &headers)?;
+
+    // auth
+    let token = authorize_scope(&shared_memory, headers, Scope::WriteEvents).await?;
+
+    let mut storage = shared_memory.event_storage.write().await;
+    let stored_event = match storage.iter_mut().find(|event| {
+        event.id.as_ref() == Some(&event_id.0) && event_visible(&shared_memory, &token, event)
+    }) {
+        Some(stored_event) => stored_event,
+        None => return Err((StatusCode::NOT_FOUND, "Event not found".to_string())),
+    };
+    if !can_modify(&shared_memory, &token, ObjectTypes::EVENT, &event_id.0) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Event created by another client".to_string(),
+        ));
+    }
+
+    let mut event = event.0;
+    let response_headers =
+        validate_resource_names(&shared_memory, &resource_names(&event.targets))?;
+
+    event.id = Some(event_id.0);
+    event.created_date_time = stored_event.created_date_time;
+    event.modification_date_time = Some(Timestamp::now());
+    event.object_type = Some(ObjectTypes::EVENT);
+    *stored_event = event.clone();
+
+    debug!("Event updated: {:?}", event);
+    notify_event_subscribers(&shared_memory, Operation::PUT, &event);
+    Ok((response_headers, Json(event)))
+}
+
+/// Handler for deleting an event
+///
+/// Subscriptions to event deletion are notified. Events created by other clients or the admin endpoints can only be
+/// deleted with the read_all scope, events the client can't see aren't found.
+///
+/// # Parameters
+/// - `headers`: The headers of the request
+/// - `shared_memory`: The shared memory state of the application
+/// - `event_id`: The ID of the event to delete as a path parameter
+///
+/// # Returns
+/// - `Result<Json<OpenADREvent>, (StatusCode, String)>`: The deleted event, or an error if the request failed
+pub async fn delete_event(
+    headers: HeaderMap,
+    shared_memory: State<Arc<AppState>>,
+    event_id: Path<String>,
+) -> Result<Json<OpenADREvent>, (StatusCode, String)> {
+    // Test session
+    let shared_memory = session_state(&shared_memory, &headers)?;
+
+    // auth
+    let token = authorize_scope(&shared_memory, headers, Scope::WriteEvents).await?;
+
+    let mut storage = shared_memory.event_storage.write().await;
+    match storage.iter().position(|event| {
+        event.id.as_ref() == Some(&event_id.0) && event_visible(&shared_memory, &token, event)
+    }) {
+        Some(_) if !can_modify(&shared_memory, &token, ObjectTypes::EVENT, &event_id.0) => Err((
+            StatusCode::FORBIDDEN,
+            "Event created by another client".to_string(),
+        )),
+        Some(index) => {
+            let event = storage.remove(index);
+            remove_owner(&shared_memory, ObjectTypes::EVENT, &event_id.0);
+            debug!("Event deleted: {:?}", event);
+            notify_event_subscribers(&shared_memory, Operation::DELETE, &event);
+            Ok(Json(event))
+        }
+        None => Err((StatusCode::NOT_FOUND, "Event not found".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::init_storage::dummy_event_to_storage;
+    use crate::utils::oauth_clients::ClientRole;
+    use crate::utils::test_helpers::{bearer, test_state};
+
+    fn event(event_name: &str) -> Json<OpenADREvent> {
+        Json(
+            serde_json::from_value(serde_json::json!({
+                "id": "client-id",
+                "createdDateTime": "2020-01-01T00:00:00Z",
+                "modificationDateTime": "2020-01-01T00:00:00Z",
+                "programID": "1",
+                "eventName": event_name,
+                "intervals": [],
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_event_crud() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "bl", ClientRole::BL, None);
+
+        // The VTN provisions the ID and timestamps
+        let (status, _, created) = post_event(headers.clone(), State(state.clone()), event("e1"))
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        let event_id = created.id.clone().unwrap();
+        assert_ne!(event_id, "client-id");
+        assert!(created.created_date_time > Some("2020-01-01T00:00:00Z".parse().unwrap()));
+        assert_eq!(created.created_date_time, created.modification_date_time);
+        assert_eq!(created.object_type, Some(ObjectTypes::EVENT));
+
+        let fetched = get_event(
+            headers.clone(),
+            State(state.clone()),
+            Path(event_id.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(fetched.event_name, Some("e1".to_string()));
+
+        // Updates keep the ID and creation timestamp
+        let (_, updated) = put_event(
+            headers.clone(),
+            State(state.clone()),
+            Path(event_id.clone()),
+            event("e2"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(updated.id, Some(event_id.clone()));
+        assert_eq!(updated.event_name, Some("e2".to_string()));
+        assert_eq!(updated.created_date_time, created.created_date_time);
+        assert!(updated.modification_date_time >= created.modification_date_time);
+
+        let deleted = delete_event(headers, State(state.clone()), Path(event_id))
+            .await
+            .unwrap();
+        assert_eq!(deleted.event_name, Some("e2".to_string()));
+        assert!(state.event_storage.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_event_write_access() {
+        let state = test_state(&[]).await;
+        dummy_event_to_storage(&state).await;
+        let ven1 = bearer(
+            &state,
+            "ven1",
+            ClientRole::VEN,
+            Some("read_targets write_events"),
+        );
+        let ven2 = bearer(
+            &state,
+            "ven2",
+            ClientRole::VEN,
+            Some("read_targets write_events"),
+        );
+        let status = |result: Result<Json<OpenADREvent>, (StatusCode, String)>| {
+            result.map(|_| ()).map_err(|err| err.0)
+        };
+
+        // The dummy event targets a resource of no client, so it isn't found
+        let dummy = || Path("dummyTest".to_string());
+        assert_eq!(
+            status(delete_event(ven1.clone(), State(state.clone()), dummy()).await),
+            Err(StatusCode::NOT_FOUND)
+        );
+        let error = put_event(ven1.clone(), State(state.clone()), dummy(), event("e1"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.0, StatusCode::NOT_FOUND);
+
+        // Visible events of the admin endpoints and other clients can't be modified
+        state.event_storage.write().await[0].targets = None;
+        let error = put_event(ven1.clone(), State(state.clone()), dummy(), event("e1"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.0, StatusCode::FORBIDDEN);
+        assert_eq!(
+            status(delete_event(ven1.clone(), State(state.clone()), dummy()).await),
+            Err(StatusCode::FORBIDDEN)
+        );
+
+        let (_, _, created) = post_event(ven1.clone(), State(state.clone()), event("e1"))
+            .await
+            .unwrap();
+        let created_id = || Path(created.id.clone().unwrap());
+        let error = put_event(
+            ven2.clone(),
+            State(state.clone()),
+            created_id(),
+            event("e2"),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.0, StatusCode::FORBIDDEN);
+        assert_eq!(
+            status(delete_event(ven2, State(state.clone()), created_id()).await),
+            Err(StatusCode::FORBIDDEN)
+        );
+
+        // The owner and read_all clients can
+        assert!(put_event(
+            ven1.clone(),
+            State(state.clone()),
+            created_id(),
+            event("e2")
+        )
+        .await
+        .is_ok());
+        assert_eq!(
+            status(delete_event(ven1, State(state.clone()), created_id()).await),
+            Ok(())
+        );
+        let bl = bearer(&state, "bl", ClientRole::BL, None);
+        assert_eq!(
+            status(delete_event(bl, State(state.clone()), dummy()).await),
+            Ok(())
+        );
+        assert!(state.event_storage.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_event_not_found() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "bl", ClientRole::BL, None);
+        let unknown = || Path("unknown".to_string());
+
+        let status = get_event(headers.clone(), State(state.clone()), unknown())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = put_event(
+            headers.clone(),
+            State(state.clone()),
+            unknown(),
+            event("e1"),
+        )
+        .await
+        .unwrap_err()
+        .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = delete_event(headers, State(state.clone()), unknown())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(state.event_storage.read().await.is_empty());
+    }
 }
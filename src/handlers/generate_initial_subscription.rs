@@ -1,6 +1,9 @@
+use crate::utils::init_storage::DUMMY_PROGRAM_ID;
+use crate::utils::iso8601::Timestamp;
 use crate::utils::openadr_models::ObjectTypes::{EVENT, SUBSCRIPTION};
 use crate::utils::openadr_models::Operation::POST;
 use crate::utils::openadr_models::{ObjectOperation, Operations, Subscription};
+use crate::utils::sessions::session_state;
 use crate::AppState;
 use axum::extract::State;
 use axum::http::{HeaderMap, StatusCode};
@@ -14,8 +17,9 @@ use std::sync::Arc;
 /// This endpoint can be useful to mimic behavior from for VTNs where the initial subscription is generated
 /// via the UI.
 ///
-/// This endpoint will always generate a subscription with the id "test", pointing towards a callback URL
-/// specified in the DEFAULT_CALLBACK_URL environment variable and will overwrite any existing subscription with the same ID.
+/// This endpoint will always generate a subscription with the id "test" to the dummy program, pointing towards a callback
+/// URL specified in the DEFAULT_CALLBACK_URL environment variable and will overwrite any existing subscription with the
+/// same ID.
 ///
 /// # Parameters
 /// - `state`: The shared memory state of the application
@@ -27,24 +31,24 @@ pub async fn post_generate_initial_subscription(
     state: State<Arc<AppState>>,
     header_map: HeaderMap,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    // Auth
-    let auth_valid = crate::utils::authorizer::authorizer(&state.secrets, header_map).await;
-    if !auth_valid {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-    }
+    // Test session
+    let state = session_state(&state, &header_map)?;
 
-    let time_now = chrono::Utc::now();
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&state, header_map).await?;
+
+    let time_now = Timestamp::now();
 
     let callback_url = std::env::var("DEFAULT_CALLBACK_URL").expect("DEFAULT_CALLBACK_URL not set!");
 
     // Create a new subscription
     let subscription = Subscription {
         id: Some("test".to_string()),
-        created_date_time: Some(time_now.to_rfc3339()),
-        modification_date_time: Some(time_now.to_rfc3339()),
+        created_date_time: Some(time_now),
+        modification_date_time: Some(time_now),
         object_type: Some(SUBSCRIPTION),
         client_name: "testing_oadr3_VEN".to_string(),
-        program_id: "test_program".to_string(),
+        program_id: DUMMY_PROGRAM_ID.to_string(),
         object_operations: vec![ObjectOperation {
             object_type: vec![EVENT],
             operations: Operations {
@@ -1,4 +1,8 @@
 use crate::utils::create_test_oadr_event::{create_test_oadr_event, EventParameters};
+use crate::utils::notifications::notify_event_subscribers;
+use crate::utils::openadr_models::Operation;
+use crate::utils::resource_validation::validate_resource_names;
+use crate::utils::sessions::session_state;
 use crate::AppState;
 use axum::extract::State;
 use axum::http::{HeaderMap, StatusCode};
@@ -7,26 +11,34 @@ use std::sync::Arc;
 
 /// Generate an event that can be polled from the get_events endpoint
 ///
-/// Takes parameters to define the event length, the oadr resource to generate event for, limits, and how far away the event should be generated in minutes
+/// Takes parameters to define the event length, the oadr resource to generate event for, limits, and when the event
+/// should start, either an absolute time or an offset from now. Negative offsets and past start times generate events
+/// that are already ongoing or over.
 /// The event will be generated and placed into the shared memory.
 /// The use case for this is that automated tests will be able to generate an event with known parameters
 /// which can then be polled and the flow of the event can be tested.
+/// Subscriptions to event creation are notified of the generated event.
+/// The resource is validated against the registered resources according to the unregistered resource policy.
 pub async fn post_generate_polled_event(
     headers: HeaderMap,
     shared_mem: State<Arc<AppState>>,
     body: Json<EventParameters>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    // auth TODO should be different auth method for these admin endpoints
-    let auth_valid = crate::utils::authorizer::authorizer(&shared_mem.secrets, headers).await;
-    if !auth_valid {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-    }
+) -> Result<(StatusCode, HeaderMap), (StatusCode, String)> {
+    // Test session
+    let shared_mem = session_state(&shared_mem, &headers)?;
+
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&shared_mem, headers).await?;
 
     // validate params
-    if body.length < 1 || body.limit_kw < 1 || body.minutes_in_future < 1 {
+    if !body.is_valid() {
         return Err((StatusCode::BAD_REQUEST, "Invalid parameters".to_string()));
     }
 
+    // validate the targeted resource according to the unregistered resource policy
+    let response_headers =
+        validate_resource_names(&shared_mem, std::slice::from_ref(&body.oadr_resource_name))?;
+
     // create the event
     let event = create_test_oadr_event(body.0).await;
 
@@ -37,6 +49,9 @@ pub async fn post_generate_polled_event(
     // log the event
     log::info!("Generated event: {:?}", event);
 
+    // notify the subscriptions, so the same stored event can be both polled and pushed
+    notify_event_subscribers(&shared_mem, Operation::POST, &event);
+
     // return success
-    Ok(StatusCode::CREATED)
+    Ok((StatusCode::CREATED, response_headers))
 }
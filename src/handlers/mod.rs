@@ -1,8 +1,15 @@
 pub(crate) mod auth;
 pub(crate) mod clear_events_list;
+pub(crate) mod deliveries;
 pub(crate) mod events;
 pub(crate) mod generate_initial_subscription;
 pub(crate) mod generate_polled_event;
 pub(crate) mod ping;
+pub(crate) mod programs;
+pub(crate) mod reports;
+pub(crate) mod resources;
+pub(crate) mod sessions;
 pub(crate) mod subscription;
+pub(crate) mod trigger_notification;
 pub(crate) mod trigger_subscription_event;
+pub(crate) mod vens;
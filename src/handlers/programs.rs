@@ -0,0 +1,311 @@
+use crate::utils::authorizer::authorize_scope;
+use crate::utils::iso8601::Timestamp;
+use crate::utils::openadr_models::{ObjectTypes, Program};
+use crate::utils::sessions::session_state;
+use crate::utils::tokens::Scope;
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use log::debug;
+use std::sync::Arc;
+
+/// Get all programs
+///
+/// Returns every program stored in the VTN. VENs use programs to resolve the metadata of the `programID`
+/// referenced by events and subscriptions.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+///
+/// # Returns
+/// - `Result<Json<Vec<Program>>, (StatusCode, String)>`: The list of programs, or an error if the request failed
+pub async fn get_programs(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<Json<Vec<Program>>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    authorize_scope(&state, header_map, Scope::ReadTargets).await?;
+
+    let programs: Vec<Program> = state
+        .programs
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect();
+
+    debug!("Returning programs: {:?}", programs);
+    Ok(Json(programs))
+}
+
+/// Get a specific program
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `program_id`: The ID of the program to get as a path parameter
+///
+/// # Returns
+/// - `Result<Json<Program>, (StatusCode, String)>`: The program, or an error if the request failed
+pub async fn get_program(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    program_id: Path<String>,
+) -> Result<Json<Program>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    authorize_scope(&state, header_map, Scope::ReadTargets).await?;
+
+    debug!("Getting program: {:?}", program_id.0);
+    let program = state.programs.get(&program_id.0);
+    match program {
+        Some(program) => Ok(Json(program.clone())),
+        None => Err((StatusCode::NOT_FOUND, "Program not found".to_string())),
+    }
+}
+
+/// Create a new program
+///
+/// The VTN provisions the ID, creation and modification timestamps of the program. Any values for these fields
+/// in the request body are ignored.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `program`: The program object to create
+///
+/// # Returns
+/// - `Result<(StatusCode, Json<Program>), (StatusCode, String)>`: The created program, or an error if the request failed
+pub async fn post_program(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    program: Json<Program>,
+) -> Result<(StatusCode, Json<Program>), (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    authorize_scope(&state, header_map, Scope::WritePrograms).await?;
+
+    // Provision the VTN controlled fields
+    let time_now = Timestamp::now();
+    let mut program = program.0;
+    program.id = Some(uuid::Uuid::new_v4().to_string());
+    program.created_date_time = Some(time_now);
+    program.modification_date_time = Some(time_now);
+    program.object_type = Some(ObjectTypes::PROGRAM);
+
+    state
+        .programs
+        .insert(program.id.clone().unwrap(), program.clone());
+
+    debug!("Program created: {:?}", program);
+    Ok((StatusCode::CREATED, Json(program)))
+}
+
+/// Update an existing program
+///
+/// The ID and creation timestamp of the stored program are kept, the modification timestamp is updated.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `program_id`: The ID of the program to update as a path parameter
+/// - `program`: The updated program object
+///
+/// # Returns
+/// - `Result<Json<Program>, (StatusCode, String)>`: The updated program, or an error if the request failed
+pub async fn put_program(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    program_id: Path<String>,
+    program: Json<Program>,
+) -> Result<Json<Program>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    authorize_scope(&state, header_map, Scope::WritePrograms).await?;
+
+    let created_date_time = match state.programs.get(&program_id.0) {
+        Some(existing) => existing.created_date_time,
+        None => return Err((StatusCode::NOT_FOUND, "Program not found".to_string())),
+    };
+
+    let mut program = program.0;
+    program.id = Some(program_id.0.clone());
+    program.created_date_time = created_date_time;
+    program.modification_date_time = Some(Timestamp::now());
+    program.object_type = Some(ObjectTypes::PROGRAM);
+
+    state.programs.insert(program_id.0, program.clone());
+
+    debug!("Program updated: {:?}", program);
+    Ok(Json(program))
+}
+
+/// Delete a specific program
+///
+/// Events and subscriptions referencing the program are left untouched.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `program_id`: The ID of the program to delete as a path parameter
+///
+/// # Returns
+/// - `Result<Json<Program>, (StatusCode, String)>`: The deleted program, or an error if the request failed
+pub async fn delete_program(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    program_id: Path<String>,
+) -> Result<Json<Program>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    authorize_scope(&state, header_map, Scope::WritePrograms).await?;
+
+    debug!("Deleting program: {:?}", program_id.0);
+    match state.programs.remove(&program_id.0) {
+        Some((_id, program)) => Ok(Json(program)),
+        None => Err((StatusCode::NOT_FOUND, "Program not found".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_test_oadr_event::{create_test_oadr_event, EventParameters};
+    use crate::utils::init_storage::dummy_event_to_storage;
+    use crate::utils::oauth_clients::ClientRole;
+    use crate::utils::test_helpers::{bearer, test_state};
+
+    fn program(program_name: &str) -> Json<Program> {
+        Json(
+            serde_json::from_value(serde_json::json!({
+                "id": "client-id",
+                "createdDateTime": "2020-01-01T00:00:00Z",
+                "programName": program_name,
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_program_crud() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "bl", ClientRole::BL, None);
+
+        // The VTN provisions the ID and timestamps
+        let (status, created) = post_program(headers.clone(), State(state.clone()), program("p1"))
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        let program_id = created.id.clone().unwrap();
+        assert_ne!(program_id, "client-id");
+        assert_ne!(
+            created.created_date_time,
+            Some("2020-01-01T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(created.created_date_time, created.modification_date_time);
+        assert_eq!(created.object_type, Some(ObjectTypes::PROGRAM));
+
+        let fetched = get_program(
+            headers.clone(),
+            State(state.clone()),
+            Path(program_id.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(fetched.program_name, "p1");
+
+        // Updates keep the ID and creation timestamp
+        let updated = put_program(
+            headers.clone(),
+            State(state.clone()),
+            Path(program_id.clone()),
+            program("p2"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(updated.id, Some(program_id.clone()));
+        assert_eq!(updated.program_name, "p2");
+        assert_eq!(updated.created_date_time, created.created_date_time);
+        assert!(updated.modification_date_time >= created.modification_date_time);
+
+        let programs = get_programs(headers.clone(), State(state.clone()))
+            .await
+            .unwrap();
+        assert_eq!(programs.len(), 1);
+
+        let deleted = delete_program(headers.clone(), State(state.clone()), Path(program_id))
+            .await
+            .unwrap();
+        assert_eq!(deleted.program_name, "p2");
+        assert!(state.programs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_program_not_found() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "bl", ClientRole::BL, None);
+        let unknown = || Path("unknown".to_string());
+
+        let status = get_program(headers.clone(), State(state.clone()), unknown())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = put_program(
+            headers.clone(),
+            State(state.clone()),
+            unknown(),
+            program("p1"),
+        )
+        .await
+        .unwrap_err()
+        .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = delete_program(headers, State(state.clone()), unknown())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Nothing was created by the failed update
+        assert!(state.programs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_event_programs_exist() {
+        let state = test_state(&[]).await;
+        dummy_event_to_storage(&state).await;
+        let headers = bearer(&state, "bl", ClientRole::BL, None);
+
+        // The programs of the dummy and generated events can be fetched
+        let dummy_event = state.event_storage.read().await[0].clone();
+        let generated = create_test_oadr_event(
+            serde_json::from_str::<EventParameters>(
+                r#"{"eventName":"e1","oadrResourceName":"resource","length":60,"minutesInFuture":5}"#,
+            )
+            .unwrap(),
+        )
+        .await;
+        for program_id in [dummy_event.program_id, generated.program_id] {
+            let fetched = get_program(
+                headers.clone(),
+                State(state.clone()),
+                Path(program_id.clone()),
+            )
+            .await
+            .unwrap();
+            assert_eq!(fetched.id, Some(program_id));
+        }
+    }
+}
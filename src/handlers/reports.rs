@@ -0,0 +1,303 @@
+use crate::utils::authorizer::authorize_scope;
+use crate::utils::client_access::{can_access, remove_owner, set_owner};
+use crate::utils::iso8601::Timestamp;
+use crate::utils::openadr_models::{ObjectTypes, Report};
+use crate::utils::sessions::session_state;
+use crate::utils::tokens::Scope;
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use log::debug;
+use std::sync::Arc;
+
+/// Get all reports
+///
+/// Returns the reports sent by the client, or every report stored in the VTN for clients with the read_all scope.
+/// Automated tests can use this to read back the reports that the VEN has sent to the VTN.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+///
+/// # Returns
+/// - `Result<Json<Vec<Report>>, (StatusCode, String)>`: The list of reports, or an error if the request failed
+pub async fn get_reports(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<Json<Vec<Report>>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::ReadTargets).await?;
+
+    let reports: Vec<Report> = state
+        .reports
+        .iter()
+        .filter(|entry| can_access(&state, &token, ObjectTypes::REPORT, entry.key()))
+        .map(|entry| entry.value().clone())
+        .collect();
+
+    debug!("Returning reports: {:?}", reports);
+    Ok(Json(reports))
+}
+
+/// Get a specific report
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `report_id`: The ID of the report to get as a path parameter
+///
+/// # Returns
+/// - `Result<Json<Report>, (StatusCode, String)>`: The report, or an error if the request failed
+pub async fn get_report(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    report_id: Path<String>,
+) -> Result<Json<Report>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::ReadTargets).await?;
+
+    debug!("Getting report: {:?}", report_id.0);
+    let report = state.reports.get(&report_id.0);
+    match report {
+        Some(report) if can_access(&state, &token, ObjectTypes::REPORT, &report_id.0) => {
+            Ok(Json(report.clone()))
+        }
+        _ => Err((StatusCode::NOT_FOUND, "Report not found".to_string())),
+    }
+}
+
+/// Create a new report
+///
+/// Reports are sent by the VEN, eg. as requested by the report descriptors of an event.
+/// The VTN provisions the ID, creation and modification timestamps of the report. Any values for these fields
+/// in the request body are ignored.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `report`: The report object to create
+///
+/// # Returns
+/// - `Result<(StatusCode, Json<Report>), (StatusCode, String)>`: The created report, or an error if the request failed
+pub async fn post_report(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    report: Json<Report>,
+) -> Result<(StatusCode, Json<Report>), (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::WriteReports).await?;
+
+    // Provision the VTN controlled fields
+    let time_now = Timestamp::now();
+    let mut report = report.0;
+    report.id = Some(uuid::Uuid::new_v4().to_string());
+    report.created_date_time = Some(time_now);
+    report.modification_date_time = Some(time_now);
+    report.object_type = Some(ObjectTypes::REPORT);
+
+    state
+        .reports
+        .insert(report.id.clone().unwrap(), report.clone());
+    set_owner(
+        &state,
+        ObjectTypes::REPORT,
+        report.id.as_ref().unwrap(),
+        &token,
+    );
+
+    debug!("Report created: {:?}", report);
+    Ok((StatusCode::CREATED, Json(report)))
+}
+
+/// Update an existing report
+///
+/// The ID and creation timestamp of the stored report are kept, the modification timestamp is updated. Reports sent
+/// by other clients can only be updated with the read_all scope.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `report_id`: The ID of the report to update as a path parameter
+/// - `report`: The updated report object
+///
+/// # Returns
+/// - `Result<Json<Report>, (StatusCode, String)>`: The updated report, or an error if the request failed
+pub async fn put_report(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    report_id: Path<String>,
+    report: Json<Report>,
+) -> Result<Json<Report>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::WriteReports).await?;
+
+    let created_date_time = match state.reports.get(&report_id.0) {
+        Some(existing) if can_access(&state, &token, ObjectTypes::REPORT, &report_id.0) => {
+            existing.created_date_time
+        }
+        _ => return Err((StatusCode::NOT_FOUND, "Report not found".to_string())),
+    };
+
+    let mut report = report.0;
+    report.id = Some(report_id.0.clone());
+    report.created_date_time = created_date_time;
+    report.modification_date_time = Some(Timestamp::now());
+    report.object_type = Some(ObjectTypes::REPORT);
+
+    state.reports.insert(report_id.0, report.clone());
+
+    debug!("Report updated: {:?}", report);
+    Ok(Json(report))
+}
+
+/// Delete a specific report
+///
+/// Reports sent by other clients can only be deleted with the read_all scope.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `report_id`: The ID of the report to delete as a path parameter
+///
+/// # Returns
+/// - `Result<Json<Report>, (StatusCode, String)>`: The deleted report, or an error if the request failed
+pub async fn delete_report(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    report_id: Path<String>,
+) -> Result<Json<Report>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::WriteReports).await?;
+
+    debug!("Deleting report: {:?}", report_id.0);
+    match state.reports.remove_if(&report_id.0, |id, _report| {
+        can_access(&state, &token, ObjectTypes::REPORT, id)
+    }) {
+        Some((_id, report)) => {
+            remove_owner(&state, ObjectTypes::REPORT, &report_id.0);
+            Ok(Json(report))
+        }
+        None => Err((StatusCode::NOT_FOUND, "Report not found".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::oauth_clients::ClientRole;
+    use crate::utils::test_helpers::{bearer, test_state};
+
+    fn report(client_name: &str) -> Json<Report> {
+        Json(
+            serde_json::from_value(serde_json::json!({
+                "id": "client-id",
+                "createdDateTime": "2020-01-01T00:00:00Z",
+                "programID": "program1",
+                "eventID": "event1",
+                "clientName": client_name,
+                "resources": [],
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_report_crud() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "ven1", ClientRole::VEN, None);
+
+        // The VTN provisions the ID and timestamps
+        let (status, created) = post_report(headers.clone(), State(state.clone()), report("ven1"))
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        let report_id = created.id.clone().unwrap();
+        assert_ne!(report_id, "client-id");
+        assert_ne!(
+            created.created_date_time,
+            Some("2020-01-01T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(created.created_date_time, created.modification_date_time);
+        assert_eq!(created.object_type, Some(ObjectTypes::REPORT));
+
+        let fetched = get_report(
+            headers.clone(),
+            State(state.clone()),
+            Path(report_id.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(fetched.client_name, "ven1");
+
+        // Updates keep the ID and creation timestamp
+        let updated = put_report(
+            headers.clone(),
+            State(state.clone()),
+            Path(report_id.clone()),
+            report("ven1-renamed"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(updated.id, Some(report_id.clone()));
+        assert_eq!(updated.client_name, "ven1-renamed");
+        assert_eq!(updated.created_date_time, created.created_date_time);
+        assert!(updated.modification_date_time >= created.modification_date_time);
+
+        let reports = get_reports(headers.clone(), State(state.clone()))
+            .await
+            .unwrap();
+        assert_eq!(reports.len(), 1);
+
+        let deleted = delete_report(headers, State(state.clone()), Path(report_id))
+            .await
+            .unwrap();
+        assert_eq!(deleted.client_name, "ven1-renamed");
+        assert!(state.reports.is_empty());
+        assert!(state.owners.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_report_not_found() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "ven1", ClientRole::VEN, None);
+        let unknown = || Path("unknown".to_string());
+
+        let status = get_report(headers.clone(), State(state.clone()), unknown())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = put_report(
+            headers.clone(),
+            State(state.clone()),
+            unknown(),
+            report("ven1"),
+        )
+        .await
+        .unwrap_err()
+        .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = delete_report(headers, State(state.clone()), unknown())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(state.reports.is_empty());
+    }
+}
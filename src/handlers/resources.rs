@@ -0,0 +1,434 @@
+use crate::utils::authorizer::authorize_scope;
+use crate::utils::client_access::{can_access, remove_owner, set_owner};
+use crate::utils::iso8601::Timestamp;
+use crate::utils::openadr_models::{ObjectTypes, Resource};
+use crate::utils::sessions::session_state;
+use crate::utils::tokens::{IssuedToken, Scope};
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use log::debug;
+use std::sync::Arc;
+
+/// Get all resources of a VEN
+///
+/// The resources of VENs registered by other clients can only be read with the read_all scope.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `ven_id`: The ID of the VEN as a path parameter
+///
+/// # Returns
+/// - `Result<Json<Vec<Resource>>, (StatusCode, String)>`: The list of resources, or an error if the request failed
+pub async fn get_resources(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    ven_id: Path<String>,
+) -> Result<Json<Vec<Resource>>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::ReadTargets).await?;
+
+    ensure_ven_accessible(&state, &token, &ven_id.0)?;
+
+    let resources: Vec<Resource> = state
+        .resources
+        .iter()
+        .filter(|resource| {
+            resource.ven_id.as_ref() == Some(&ven_id.0)
+                && can_access(&state, &token, ObjectTypes::RESOURCE, resource.key())
+        })
+        .map(|entry| entry.value().clone())
+        .collect();
+
+    debug!("Returning resources: {:?}", resources);
+    Ok(Json(resources))
+}
+
+/// Get a specific resource of a VEN
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `path`: The ID of the VEN and the ID of the resource as path parameters
+///
+/// # Returns
+/// - `Result<Json<Resource>, (StatusCode, String)>`: The resource, or an error if the request failed
+pub async fn get_resource(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    path: Path<(String, String)>,
+) -> Result<Json<Resource>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::ReadTargets).await?;
+
+    let (ven_id, resource_id) = path.0;
+    ensure_ven_accessible(&state, &token, &ven_id)?;
+
+    debug!("Getting resource: {:?}", resource_id);
+    let resource = state.resources.get(&resource_id);
+    match resource {
+        Some(resource) if resource_accessible(&state, &token, &ven_id, &resource) => {
+            Ok(Json(resource.clone()))
+        }
+        _ => Err((StatusCode::NOT_FOUND, "Resource not found".to_string())),
+    }
+}
+
+/// Create a new resource for a VEN
+///
+/// The VTN provisions the ID, VEN ID, creation and modification timestamps of the resource. Any values for these
+/// fields in the request body are ignored. Resource names have to be unique across all VENs, as events target
+/// resources by name. Resources can only be created for VENs registered by other clients with the read_all scope.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `ven_id`: The ID of the VEN as a path parameter
+/// - `resource`: The resource object to create
+///
+/// # Returns
+/// - `Result<(StatusCode, Json<Resource>), (StatusCode, String)>`: The created resource, or an error if the request failed
+pub async fn post_resource(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    ven_id: Path<String>,
+    resource: Json<Resource>,
+) -> Result<(StatusCode, Json<Resource>), (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::WriteVens).await?;
+
+    ensure_ven_accessible(&state, &token, &ven_id.0)?;
+
+    let mut resource = resource.0;
+    if resource_name_taken(&state, &resource.resource_name, None) {
+        return Err((
+            StatusCode::CONFLICT,
+            "Resource name already exists".to_string(),
+        ));
+    }
+
+    // Provision the VTN controlled fields
+    let time_now = Timestamp::now();
+    resource.id = Some(uuid::Uuid::new_v4().to_string());
+    resource.ven_id = Some(ven_id.0);
+    resource.created_date_time = Some(time_now);
+    resource.modification_date_time = Some(time_now);
+    resource.object_type = Some(ObjectTypes::RESOURCE);
+
+    state
+        .resources
+        .insert(resource.id.clone().unwrap(), resource.clone());
+    set_owner(
+        &state,
+        ObjectTypes::RESOURCE,
+        resource.id.as_ref().unwrap(),
+        &token,
+    );
+
+    debug!("Resource created: {:?}", resource);
+    Ok((StatusCode::CREATED, Json(resource)))
+}
+
+/// Update an existing resource of a VEN
+///
+/// The ID, VEN ID and creation timestamp of the stored resource are kept, the modification timestamp is updated.
+/// Resources created by other clients can only be updated with the read_all scope.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `path`: The ID of the VEN and the ID of the resource as path parameters
+/// - `resource`: The updated resource object
+///
+/// # Returns
+/// - `Result<Json<Resource>, (StatusCode, String)>`: The updated resource, or an error if the request failed
+pub async fn put_resource(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    path: Path<(String, String)>,
+    resource: Json<Resource>,
+) -> Result<Json<Resource>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::WriteVens).await?;
+
+    let (ven_id, resource_id) = path.0;
+    ensure_ven_accessible(&state, &token, &ven_id)?;
+
+    let created_date_time = match state.resources.get(&resource_id) {
+        Some(existing) if resource_accessible(&state, &token, &ven_id, &existing) => {
+            existing.created_date_time
+        }
+        _ => return Err((StatusCode::NOT_FOUND, "Resource not found".to_string())),
+    };
+
+    let mut resource = resource.0;
+    if resource_name_taken(&state, &resource.resource_name, Some(&resource_id)) {
+        return Err((
+            StatusCode::CONFLICT,
+            "Resource name already exists".to_string(),
+        ));
+    }
+
+    resource.id = Some(resource_id.clone());
+    resource.ven_id = Some(ven_id);
+    resource.created_date_time = created_date_time;
+    resource.modification_date_time = Some(Timestamp::now());
+    resource.object_type = Some(ObjectTypes::RESOURCE);
+
+    state.resources.insert(resource_id, resource.clone());
+
+    debug!("Resource updated: {:?}", resource);
+    Ok(Json(resource))
+}
+
+/// Delete a specific resource of a VEN
+///
+/// Resources created by other clients can only be deleted with the read_all scope.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `path`: The ID of the VEN and the ID of the resource as path parameters
+///
+/// # Returns
+/// - `Result<Json<Resource>, (StatusCode, String)>`: The deleted resource, or an error if the request failed
+pub async fn delete_resource(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    path: Path<(String, String)>,
+) -> Result<Json<Resource>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::WriteVens).await?;
+
+    let (ven_id, resource_id) = path.0;
+    ensure_ven_accessible(&state, &token, &ven_id)?;
+
+    debug!("Deleting resource: {:?}", resource_id);
+    match state.resources.remove_if(&resource_id, |_id, resource| {
+        resource_accessible(&state, &token, &ven_id, resource)
+    }) {
+        Some((_id, resource)) => {
+            remove_owner(&state, ObjectTypes::RESOURCE, &resource_id);
+            Ok(Json(resource))
+        }
+        None => Err((StatusCode::NOT_FOUND, "Resource not found".to_string())),
+    }
+}
+
+/// Respond with 404 if the VEN of the resource path doesn't exist or is registered by another client
+fn ensure_ven_accessible(
+    state: &AppState,
+    token: &IssuedToken,
+    ven_id: &str,
+) -> Result<(), (StatusCode, String)> {
+    if !state.vens.contains_key(ven_id) || !can_access(state, token, ObjectTypes::VEN, ven_id) {
+        return Err((StatusCode::NOT_FOUND, "VEN not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Check whether the resource belongs to the VEN of the resource path and the client can access it
+fn resource_accessible(
+    state: &AppState,
+    token: &IssuedToken,
+    ven_id: &str,
+    resource: &Resource,
+) -> bool {
+    resource.ven_id.as_deref() == Some(ven_id)
+        && resource
+            .id
+            .as_ref()
+            .is_some_and(|id| can_access(state, token, ObjectTypes::RESOURCE, id))
+}
+
+/// Check whether a resource name is already used by another resource than the one being updated
+fn resource_name_taken(
+    state: &AppState,
+    resource_name: &str,
+    resource_id: Option<&String>,
+) -> bool {
+    state.resources.iter().any(|resource| {
+        resource.resource_name == resource_name && Some(resource.key()) != resource_id
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::vens::post_ven;
+    use crate::utils::oauth_clients::ClientRole;
+    use crate::utils::openadr_models::Ven;
+    use crate::utils::test_helpers::{bearer, test_state};
+
+    fn resource(resource_name: &str) -> Json<Resource> {
+        Json(
+            serde_json::from_value(serde_json::json!({
+                "id": "client-id",
+                "venID": "client-ven-id",
+                "resourceName": resource_name,
+            }))
+            .unwrap(),
+        )
+    }
+
+    async fn register_ven(state: &Arc<AppState>, headers: &HeaderMap, ven_name: &str) -> String {
+        let ven: Ven = serde_json::from_value(serde_json::json!({"venName": ven_name})).unwrap();
+        let (_, ven) = post_ven(headers.clone(), State(state.clone()), Json(ven))
+            .await
+            .unwrap();
+        ven.id.clone().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_resource_crud() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "ven1", ClientRole::VEN, None);
+        let ven_id = register_ven(&state, &headers, "ven1").await;
+
+        // The VTN provisions the IDs and timestamps
+        let (status, created) = post_resource(
+            headers.clone(),
+            State(state.clone()),
+            Path(ven_id.clone()),
+            resource("resource1"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        let resource_id = created.id.clone().unwrap();
+        assert_ne!(resource_id, "client-id");
+        assert_eq!(created.ven_id, Some(ven_id.clone()));
+        assert_eq!(created.created_date_time, created.modification_date_time);
+        let path = || Path((ven_id.clone(), resource_id.clone()));
+
+        // Resource names are unique across all VENs
+        let status = post_resource(
+            headers.clone(),
+            State(state.clone()),
+            Path(ven_id.clone()),
+            resource("resource1"),
+        )
+        .await
+        .unwrap_err()
+        .0;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let updated = put_resource(
+            headers.clone(),
+            State(state.clone()),
+            path(),
+            resource("resource2"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(updated.id, Some(resource_id.clone()));
+        assert_eq!(updated.created_date_time, created.created_date_time);
+
+        let deleted = delete_resource(headers.clone(), State(state.clone()), path())
+            .await
+            .unwrap();
+        assert_eq!(deleted.resource_name, "resource2");
+        let status = get_resource(headers, State(state.clone()), path())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(!state
+            .owners
+            .contains_key(&(ObjectTypes::RESOURCE, resource_id.clone())));
+    }
+
+    #[tokio::test]
+    async fn test_resource_ownership() {
+        let state = test_state(&[]).await;
+        let ven1 = bearer(
+            &state,
+            "ven1",
+            ClientRole::VEN,
+            Some("read_targets write_vens"),
+        );
+        let ven2 = bearer(
+            &state,
+            "ven2",
+            ClientRole::VEN,
+            Some("read_targets write_vens"),
+        );
+        let ven_id = register_ven(&state, &ven1, "ven1").await;
+        let (_, created) = post_resource(
+            ven1.clone(),
+            State(state.clone()),
+            Path(ven_id.clone()),
+            resource("resource1"),
+        )
+        .await
+        .unwrap();
+        let path = || Path((ven_id.clone(), created.id.clone().unwrap()));
+
+        // The resources of other clients' VENs are not found
+        let status = get_resources(ven2.clone(), State(state.clone()), Path(ven_id.clone()))
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = post_resource(
+            ven2.clone(),
+            State(state.clone()),
+            Path(ven_id.clone()),
+            resource("resource2"),
+        )
+        .await
+        .unwrap_err()
+        .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = put_resource(
+            ven2.clone(),
+            State(state.clone()),
+            path(),
+            resource("resource2"),
+        )
+        .await
+        .unwrap_err()
+        .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = delete_resource(ven2.clone(), State(state.clone()), path())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Other clients can't claim the resource name for their own VENs
+        let other_ven_id = register_ven(&state, &ven2, "ven2").await;
+        let status = post_resource(
+            ven2,
+            State(state.clone()),
+            Path(other_ven_id),
+            resource("resource1"),
+        )
+        .await
+        .unwrap_err()
+        .0;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let resources = get_resources(ven1, State(state.clone()), Path(ven_id))
+            .await
+            .unwrap();
+        assert_eq!(resources.len(), 1);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::utils::init_storage::{dummy_event_to_storage, init_session_storage};
+use crate::utils::iso8601::Timestamp;
+use crate::utils::sessions::{valid_session_id, SessionInfo, TestSession};
+use crate::AppState;
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use dashmap::mapref::entry::Entry;
+use log::{debug, info};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Parameters for creating a test session
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SessionParameters {
+    /// ID of the session, generated by the VTN if not given
+    pub id: Option<String>,
+    /// Clients bound to the session
+    #[serde(default)]
+    pub clients: Vec<String>,
+}
+
+/// Create a test session
+///
+/// The session is an isolated namespace of the VTN with its own storage and delivery log, starting with the dummy event
+/// like a freshly started VTN. Requests select the session with the `X-Test-Session` header, or implicitly when the
+/// calling client is bound to the session. A client can only be bound to a single session.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `body`: Optional session parameters as JSON, the defaults are used for an empty body
+///
+/// # Returns
+/// - `Result<(StatusCode, Json<SessionInfo>), (StatusCode, String)>`: The created session, or an error if the request
+///   failed
+pub async fn post_session(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<SessionInfo>), (StatusCode, String)> {
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&state, header_map).await?;
+
+    let parameters: SessionParameters = if body.is_empty() {
+        SessionParameters::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|error| {
+            debug!("Invalid session parameters: {}", error);
+            (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid session parameters: {}", error),
+            )
+        })?
+    };
+    let session_id = parameters
+        .id
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    if !valid_session_id(&session_id) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid session ID".to_string()));
+    }
+    let session = TestSession {
+        created_date_time: Timestamp::now(),
+        clients: parameters.clients,
+        state: init_session_storage(&state),
+    };
+    dummy_event_to_storage(&session.state).await;
+    let info = session.info(&session_id).await;
+
+    // Check the bound clients and store the session under the same lock, so that concurrent requests can't bind a
+    // client to two sessions
+    let _session_lock = state
+        .session_lock
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if state.sessions.iter().any(|session| {
+        session
+            .clients
+            .iter()
+            .any(|client| info.clients.contains(client))
+    }) {
+        return Err((
+            StatusCode::CONFLICT,
+            "Client already bound to a session".to_string(),
+        ));
+    }
+    match state.sessions.entry(session_id) {
+        Entry::Occupied(_) => Err((
+            StatusCode::CONFLICT,
+            "Session ID already in use".to_string(),
+        )),
+        Entry::Vacant(entry) => {
+            entry.insert(session);
+            info!("Test session created: {:?}", info);
+            Ok((StatusCode::CREATED, Json(info)))
+        }
+    }
+}
+
+/// Get all test sessions
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+///
+/// # Returns
+/// - `Result<Json<Vec<SessionInfo>>, (StatusCode, String)>`: The sessions ordered by creation time, or an error if the
+///   request failed
+pub async fn get_sessions(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<Json<Vec<SessionInfo>>, (StatusCode, String)> {
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&state, header_map).await?;
+
+    // Collect the session states first to avoid holding the map locks across awaits
+    let sessions: Vec<(String, TestSession)> = state
+        .sessions
+        .iter()
+        .map(|session| (session.key().clone(), session.value().clone()))
+        .collect();
+
+    let mut infos: Vec<SessionInfo> = Vec::new();
+    for (session_id, session) in sessions {
+        infos.push(session.info(&session_id).await);
+    }
+    infos.sort_by_key(|info| info.created_date_time);
+
+    Ok(Json(infos))
+}
+
+/// Tear down a test session
+///
+/// Removes the session including all its storage and delivery log. Clients bound to the session use the state of the
+/// application again.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `session_id`: The ID of the session to delete as a path parameter
+///
+/// # Returns
+/// - `Result<Json<SessionInfo>, (StatusCode, String)>`: The deleted session, or an error if the request failed
+pub async fn delete_session(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    session_id: Path<String>,
+) -> Result<Json<SessionInfo>, (StatusCode, String)> {
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&state, header_map).await?;
+
+    match state.sessions.remove(&session_id.0) {
+        Some((session_id, session)) => {
+            let info = session.info(&session_id).await;
+            info!("Test session deleted: {:?}", info);
+            Ok(Json(info))
+        }
+        None => Err((StatusCode::NOT_FOUND, "Test session not found".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::oauth_clients::ClientRole;
+    use crate::utils::test_helpers::{bearer, test_state};
+
+    #[tokio::test]
+    async fn test_post_session() {
+        let state = test_state(&[]).await;
+        let admin = bearer(&state, "bl", ClientRole::BL, None);
+
+        // An empty body uses the defaults
+        let (status, created) = post_session(admin.clone(), State(state.clone()), Bytes::new())
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(created.0.clients.is_empty());
+        assert_eq!(created.0.event_count, 1);
+        assert!(state.sessions.contains_key(&created.0.id));
+
+        let (_, created) = post_session(
+            admin.clone(),
+            State(state.clone()),
+            Bytes::from(r#"{"id": "suite-a", "clients": ["ven1"]}"#),
+        )
+        .await
+        .unwrap();
+        assert_eq!(created.0.id, "suite-a");
+        assert_eq!(created.0.clients, vec!["ven1".to_string()]);
+
+        // Session IDs and bound clients are unique
+        let error = post_session(
+            admin.clone(),
+            State(state.clone()),
+            Bytes::from(r#"{"id": "suite-a"}"#),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.0, StatusCode::CONFLICT);
+        let error = post_session(
+            admin,
+            State(state.clone()),
+            Bytes::from(r#"{"clients": ["ven2", "ven1"]}"#),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.0, StatusCode::CONFLICT);
+        assert_eq!(state.sessions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_post_session_malformed_body() {
+        let state = test_state(&[]).await;
+        let admin = bearer(&state, "bl", ClientRole::BL, None);
+
+        for body in ["{", r#"{"clients": "ven1"}"#, r#"{"client": ["ven1"]}"#] {
+            let error = post_session(admin.clone(), State(state.clone()), Bytes::from(body))
+                .await
+                .unwrap_err();
+            assert_eq!(error.0, StatusCode::BAD_REQUEST);
+        }
+        assert!(state.sessions.is_empty());
+    }
+}
@@ -1,8 +1,13 @@
-use crate::utils::openadr_models::Subscription;
+use crate::utils::authorizer::authorize_scope;
+use crate::utils::client_access::{can_modify, remove_owner, set_owner};
+use crate::utils::openadr_models::{ObjectTypes, Subscription};
+use crate::utils::sessions::session_state;
+use crate::utils::tokens::Scope;
 use crate::AppState;
 use axum::extract::{Path, State};
 use axum::http::HeaderMap;
 use axum::Json;
+use dashmap::mapref::entry::Entry;
 use log::debug;
 use reqwest::StatusCode;
 use std::sync::Arc;
@@ -11,7 +16,8 @@ use std::sync::Arc;
 ///
 /// Subscriptions can be used by the VEN receive event/program notifications from the VTN server.
 /// For the purposes of the testing tool, the VTN has to support creating and modifying of the subscription
-/// so that the tool maintains a valid access token and callback URL.
+/// so that the tool maintains a valid access token and callback URL. Subscriptions created by other clients can't be
+/// overwritten without the read_all scope, neither can subscriptions created by the admin endpoints.
 ///
 /// # Parameters
 /// - `header_map`: The headers of the request
@@ -25,31 +31,46 @@ pub async fn post_subscription(
     state: State<Arc<AppState>>,
     subscription: Json<Subscription>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
     // Auth
-    let auth_valid = crate::utils::authorizer::authorizer(&state.secrets, header_map).await;
-    if !auth_valid {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-    }
+    let token = authorize_scope(&state, header_map, Scope::WriteSubscriptions).await?;
 
     // Marshall the subscription
     let subscription = subscription.0;
 
     // Validate that an ID exists. Unlike the actual standard, in reality this is actually required every time
-    if subscription.id.is_none() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Subscription ID is required".to_string(),
-        ));
-    }
+    let subscription_id = match &subscription.id {
+        Some(subscription_id) => subscription_id.clone(),
+        None => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Subscription ID is required".to_string(),
+            ))
+        }
+    };
 
     // Store the subscription
     //
-    // If this was a real VTN, there should definitely be some more validation here to ensure there's
-    // no multiple customers subscriptions etc, but for the purposes of the testing tool, we'll just ignore any
-    // existing data and overwrite it
-    let _storage = state
-        .subscriptions
-        .insert(subscription.clone().id.unwrap(), subscription.clone());
+    // If this was a real VTN, there should definitely be some more validation here, but for the purposes of the
+    // testing tool, we'll just overwrite any existing subscription of the client. The owner of a new subscription is
+    // recorded so that other clients can't access it
+    match state.subscriptions.entry(subscription_id.clone()) {
+        Entry::Occupied(mut existing) => {
+            if !can_modify(&state, &token, ObjectTypes::SUBSCRIPTION, &subscription_id) {
+                return Err((
+                    StatusCode::CONFLICT,
+                    "Subscription ID already in use".to_string(),
+                ));
+            }
+            existing.insert(subscription.clone());
+        }
+        Entry::Vacant(vacant) => {
+            vacant.insert(subscription.clone());
+            set_owner(&state, ObjectTypes::SUBSCRIPTION, &subscription_id, &token);
+        }
+    }
 
     debug!("Subscription created/updated: {:?}", subscription);
 
@@ -58,9 +79,8 @@ pub async fn post_subscription(
 
 /// Get all subscriptions
 ///
-/// This function returns all the subscriptions that have been created in the VTN.
-/// In a real VTN, this would be used to list all the subscriptions for the VEN/client, but we have no granular
-/// access control in this tool so it just returns all the subscriptions.
+/// This function returns the subscriptions of the client. Clients with the read_all scope get all the subscriptions
+/// that have been created in the VTN, including the subscriptions created by the admin endpoints.
 ///
 /// # Parameters
 /// - `header_map`: The headers of the request
@@ -72,19 +92,21 @@ pub async fn get_subscriptions(
     header_map: HeaderMap,
     state: State<Arc<AppState>>,
 ) -> Result<Json<Vec<Subscription>>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
     // Auth
-    let auth_valid = crate::utils::authorizer::authorizer(&state.secrets, header_map).await;
-    if !auth_valid {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-    }
+    let token = authorize_scope(&state, header_map, Scope::ReadTargets).await?;
 
     // Get the subscriptions
     let subscriptions = state.subscriptions.clone();
 
-    // return all the subscriptions
+    // return the subscriptions the client can access
     let mut subscriptions_array: Vec<Subscription> = vec![];
-    for (_key, subscription) in subscriptions {
-        subscriptions_array.push(subscription);
+    for (key, subscription) in subscriptions {
+        if can_modify(&state, &token, ObjectTypes::SUBSCRIPTION, &key) {
+            subscriptions_array.push(subscription);
+        }
     }
 
     debug!("Returning subscriptions: {:?}", subscriptions_array);
@@ -93,7 +115,8 @@ pub async fn get_subscriptions(
 
 /// Get a specific subscription
 ///
-/// This function returns a specific subscription that has been created in the VTN.
+/// This function returns a specific subscription of the client. Subscriptions of other clients and the admin endpoints
+/// are only returned with the read_all scope.
 ///
 /// # Parameters
 /// - `header_map`: The headers of the request
@@ -104,10 +127,18 @@ pub async fn get_subscription(
     state: State<Arc<AppState>>,
     subscription_id: Path<String>,
 ) -> Result<Json<Subscription>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
     // Auth
-    let auth_valid = crate::utils::authorizer::authorizer(&state.secrets, header_map).await;
-    if !auth_valid {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
+    let token = authorize_scope(&state, header_map, Scope::ReadTargets).await?;
+    if !can_modify(
+        &state,
+        &token,
+        ObjectTypes::SUBSCRIPTION,
+        &subscription_id.0,
+    ) {
+        return Err((StatusCode::NOT_FOUND, "Subscription not found".to_string()));
     }
 
     debug!("Getting subscription: {:?}", subscription_id.0);
@@ -124,8 +155,7 @@ pub async fn get_subscription(
 /// Delete a specific subscription
 ///
 /// This function deletes a specific subscription that has been created in the VTN.
-/// Normally there should be access control to ensure that the user has the correct permissions to delete the subscription,
-/// but for the purposes of the testing tool, there's no granular access control so anyone can delete any subscription.
+/// Clients can only delete their own subscriptions, unless they have the read_all scope.
 ///
 /// # Parameters
 /// - `header_map`: The headers of the request
@@ -139,27 +169,37 @@ pub async fn delete_subscription(
     state: State<Arc<AppState>>,
     subscription_id: Path<String>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
     // Auth
-    let auth_valid = crate::utils::authorizer::authorizer(&state.secrets, header_map).await;
-    if !auth_valid {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-    }
+    let token = authorize_scope(&state, header_map, Scope::WriteSubscriptions).await?;
 
     debug!("Deleting subscription: {:?}", subscription_id.0);
 
-    // Ensure the subscription exists
-    if !state.subscriptions.contains_key(&subscription_id.0) {
+    // Ensure the subscription exists and belongs to the client
+    if !state.subscriptions.contains_key(&subscription_id.0)
+        || !can_modify(
+            &state,
+            &token,
+            ObjectTypes::SUBSCRIPTION,
+            &subscription_id.0,
+        )
+    {
         return Err((StatusCode::NOT_FOUND, "Subscription not found".to_string()));
     }
 
     // Delete the subscription
     let _deleted = state.subscriptions.remove(&subscription_id.0);
+    remove_owner(&state, ObjectTypes::SUBSCRIPTION, &subscription_id.0);
 
     Ok(StatusCode::OK)
 }
 
 /// Update an existing subscription
 ///
+/// Subscriptions created by other clients or the admin endpoints can only be updated with the read_all scope.
+///
 /// # Parameters
 /// - `header_map`: The headers of the request
 /// - `state`: The shared memory state of the application
@@ -174,11 +214,11 @@ pub async fn put_subscription(
     subscription_id: Path<String>,
     subscription: Json<Subscription>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
     // Auth
-    let auth_valid = crate::utils::authorizer::authorizer(&state.secrets, header_map).await;
-    if !auth_valid {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-    }
+    let token = authorize_scope(&state, header_map, Scope::WriteSubscriptions).await?;
 
     // Marshall the subscription
     let subscription = subscription.0;
@@ -190,8 +230,15 @@ pub async fn put_subscription(
             "Subscription ID mismatch".to_string(),
         ));
     }
-    // Check that the subscription exists
-    if !state.subscriptions.contains_key(&subscription_id.0) {
+    // Check that the subscription exists and belongs to the client
+    if !state.subscriptions.contains_key(&subscription_id.0)
+        || !can_modify(
+            &state,
+            &token,
+            ObjectTypes::SUBSCRIPTION,
+            &subscription_id.0,
+        )
+    {
         return Err((StatusCode::NOT_FOUND, "Subscription not found".to_string()));
     }
 
@@ -208,3 +255,153 @@ pub async fn put_subscription(
 
     Ok(StatusCode::OK)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::oauth_clients::ClientRole;
+    use crate::utils::test_helpers::{
+        bearer, store_subscription, test_state, UNREACHABLE_CALLBACK_URL,
+    };
+
+    #[tokio::test]
+    async fn test_admin_subscription_access() {
+        let state = test_state(&[]).await;
+        store_subscription(
+            &state,
+            "test",
+            ObjectTypes::EVENT,
+            &[UNREACHABLE_CALLBACK_URL],
+        );
+        let subscription = state.subscriptions.get("test").unwrap().clone();
+        let ven1 = bearer(
+            &state,
+            "ven1",
+            ClientRole::VEN,
+            Some("read_targets write_subscriptions"),
+        );
+
+        // Subscriptions of the admin endpoints, including their bearer tokens, are hidden from the clients
+        let subscriptions = get_subscriptions(ven1.clone(), State(state.clone()))
+            .await
+            .unwrap();
+        assert!(subscriptions.0.is_empty());
+        let error = get_subscription(ven1.clone(), State(state.clone()), Path("test".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(error.0, StatusCode::NOT_FOUND);
+
+        // and can't be taken over
+        let error = put_subscription(
+            ven1.clone(),
+            State(state.clone()),
+            Path("test".to_string()),
+            Json(subscription.clone()),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.0, StatusCode::NOT_FOUND);
+        let error = post_subscription(
+            ven1.clone(),
+            State(state.clone()),
+            Json(subscription.clone()),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.0, StatusCode::CONFLICT);
+        let error = delete_subscription(ven1, State(state.clone()), Path("test".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(error.0, StatusCode::NOT_FOUND);
+        assert!(state.subscriptions.contains_key("test"));
+
+        // read_all clients see and update it without taking ownership
+        let bl = bearer(
+            &state,
+            "bl",
+            ClientRole::BL,
+            Some("read_all write_subscriptions"),
+        );
+        let status = put_subscription(
+            bl.clone(),
+            State(state.clone()),
+            Path("test".to_string()),
+            Json(subscription),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::OK);
+        let subscriptions = get_subscriptions(bl, State(state.clone())).await.unwrap();
+        assert_eq!(subscriptions.0.len(), 1);
+        assert!(!state
+            .owners
+            .contains_key(&(ObjectTypes::SUBSCRIPTION, "test".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_ownership() {
+        let state = test_state(&[]).await;
+        store_subscription(
+            &state,
+            "template",
+            ObjectTypes::EVENT,
+            &[UNREACHABLE_CALLBACK_URL],
+        );
+        let mut subscription = state.subscriptions.get("template").unwrap().clone();
+        subscription.id = Some("sub1".to_string());
+        let ven1 = bearer(
+            &state,
+            "ven1",
+            ClientRole::VEN,
+            Some("read_targets write_subscriptions"),
+        );
+        let ven2 = bearer(
+            &state,
+            "ven2",
+            ClientRole::VEN,
+            Some("read_targets write_subscriptions"),
+        );
+
+        // The creating client owns the subscription
+        for _ in 0..2 {
+            let status = post_subscription(
+                ven1.clone(),
+                State(state.clone()),
+                Json(subscription.clone()),
+            )
+            .await
+            .unwrap();
+            assert_eq!(status, StatusCode::OK);
+        }
+        let fetched =
+            get_subscription(ven1.clone(), State(state.clone()), Path("sub1".to_string()))
+                .await
+                .unwrap();
+        assert_eq!(fetched.0.id, Some("sub1".to_string()));
+
+        let error = post_subscription(
+            ven2.clone(),
+            State(state.clone()),
+            Json(subscription.clone()),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.0, StatusCode::CONFLICT);
+        let error = get_subscription(ven2.clone(), State(state.clone()), Path("sub1".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(error.0, StatusCode::NOT_FOUND);
+        let error = delete_subscription(ven2, State(state.clone()), Path("sub1".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(error.0, StatusCode::NOT_FOUND);
+
+        let status = delete_subscription(ven1, State(state.clone()), Path("sub1".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert!(!state
+            .owners
+            .contains_key(&(ObjectTypes::SUBSCRIPTION, "sub1".to_string())));
+    }
+}
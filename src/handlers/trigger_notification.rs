@@ -0,0 +1,345 @@
+use crate::handlers::trigger_subscription_event::{
+    deliver_to_subscription, trigger_response, TriggerQuery,
+};
+use crate::utils::openadr_models::{
+    Notification, NotificationObject, ObjectTypes, OpenADREvent, Operation, Program, Report,
+    Resource, Subscription, Ven,
+};
+use crate::utils::sessions::session_state;
+use crate::utils::webhook_delivery::DeliveryRecord;
+use crate::AppState;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use log::debug;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Parameters for triggering a notification of any object type
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationParameters {
+    /// Type of the object to send
+    pub object_type: ObjectTypes,
+    /// Operation to report in the notification, defaults to POST
+    #[serde(default = "default_operation")]
+    pub operation: Operation,
+    /// ID of a stored object to send
+    #[serde(rename = "objectID")]
+    pub object_id: Option<String>,
+    /// The object to send as is, used when `objectID` is not given
+    pub object: Option<serde_json::Value>,
+}
+
+fn default_operation() -> Operation {
+    Operation::POST
+}
+
+/// Send a notification of any object type to every subscription interested in the object type
+///
+/// The object is either looked up from storage with `objectID`, or given as is in `object`. Every subscription with
+/// an object operation including the object type is notified, regardless of the subscribed operations, program
+/// and targets.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `query`: Optional `failOnDeliveryError` query parameter
+/// - `body`: The notification parameters
+///
+/// # Returns
+/// - `Result<(StatusCode, Json<Vec<DeliveryRecord>>), (StatusCode, String)>`: The delivery records, or an error if
+///   the request failed
+pub async fn post_trigger_notification(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    query: Query<TriggerQuery>,
+    body: Json<NotificationParameters>,
+) -> Result<(StatusCode, Json<Vec<DeliveryRecord>>), (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&state, header_map).await?;
+
+    let notification = build_notification(&state, body.0).await?;
+
+    let subscription_ids: Vec<String> = state
+        .subscriptions
+        .iter()
+        .filter(|subscription| {
+            subscription
+                .object_operations
+                .iter()
+                .any(|operation| operation.object_type.contains(&notification.object_type))
+        })
+        .map(|subscription| subscription.key().clone())
+        .collect();
+
+    let mut deliveries = vec![];
+    for subscription_id in subscription_ids {
+        // Subscriptions deleted in the meantime are skipped
+        if let Ok(records) = deliver_to_subscription(&state, &subscription_id, &notification).await
+        {
+            deliveries.extend(records);
+        }
+    }
+
+    Ok(trigger_response(deliveries, &query))
+}
+
+/// Send a notification of any object type to the VEN according to the specified subscription
+///
+/// Same as `post_trigger_notification`, but only the subscription in the path parameter is notified.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `subscription_id`: The ID of the subscription to send the notification to
+/// - `state`: The shared memory state of the application
+/// - `query`: Optional `failOnDeliveryError` query parameter
+/// - `body`: The notification parameters
+///
+/// # Returns
+/// - `Result<(StatusCode, Json<Vec<DeliveryRecord>>), (StatusCode, String)>`: The delivery records, or an error if
+///   the request failed
+pub async fn post_trigger_subscription_notification(
+    header_map: HeaderMap,
+    subscription_id: Path<String>,
+    state: State<Arc<AppState>>,
+    query: Query<TriggerQuery>,
+    body: Json<NotificationParameters>,
+) -> Result<(StatusCode, Json<Vec<DeliveryRecord>>), (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&state, header_map).await?;
+
+    let notification = build_notification(&state, body.0).await?;
+    let deliveries = deliver_to_subscription(&state, &subscription_id.0, &notification).await?;
+
+    Ok(trigger_response(deliveries, &query))
+}
+
+/// Build a notification from the notification parameters
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `parameters`: The notification parameters
+///
+/// # Returns
+/// - `Result<Notification, (StatusCode, String)>`: The notification, or an error if the object couldn't be resolved
+async fn build_notification(
+    state: &AppState,
+    parameters: NotificationParameters,
+) -> Result<Notification, (StatusCode, String)> {
+    debug!("Building notification with parameters: {:?}", parameters);
+
+    let object = match (&parameters.object_id, parameters.object) {
+        (Some(object_id), _) => stored_object(state, &parameters.object_type, object_id).await?,
+        (None, Some(object)) => given_object(&parameters.object_type, object)?,
+        (None, None) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Either objectID or object is required".to_string(),
+            ))
+        }
+    };
+
+    let targets = match &object {
+        NotificationObject::Event(event) => event.targets.clone(),
+        NotificationObject::Program(program) => program.targets.clone(),
+        NotificationObject::Subscription(subscription) => subscription.targets.clone(),
+        NotificationObject::Ven(ven) => ven.targets.clone(),
+        NotificationObject::Resource(resource) => resource.targets.clone(),
+        NotificationObject::Report(_) => None,
+    };
+
+    Ok(Notification {
+        object_type: parameters.object_type,
+        operation: parameters.operation,
+        targets,
+        object,
+    })
+}
+
+/// Look up a stored object by its type and ID
+async fn stored_object(
+    state: &AppState,
+    object_type: &ObjectTypes,
+    object_id: &str,
+) -> Result<NotificationObject, (StatusCode, String)> {
+    let object = match object_type {
+        ObjectTypes::EVENT => state
+            .event_storage
+            .read()
+            .await
+            .iter()
+            .find(|event| event.id.as_deref() == Some(object_id))
+            .cloned()
+            .map(NotificationObject::Event),
+        ObjectTypes::PROGRAM => state
+            .programs
+            .get(object_id)
+            .map(|program| NotificationObject::Program(program.clone())),
+        ObjectTypes::REPORT => state
+            .reports
+            .get(object_id)
+            .map(|report| NotificationObject::Report(report.clone())),
+        ObjectTypes::SUBSCRIPTION => state
+            .subscriptions
+            .get(object_id)
+            .map(|subscription| NotificationObject::Subscription(subscription.clone())),
+        ObjectTypes::VEN => state
+            .vens
+            .get(object_id)
+            .map(|ven| NotificationObject::Ven(ven.clone())),
+        ObjectTypes::RESOURCE => state
+            .resources
+            .get(object_id)
+            .map(|resource| NotificationObject::Resource(resource.clone())),
+    };
+
+    object.ok_or((
+        StatusCode::NOT_FOUND,
+        format!("{} not found", object_type.as_str()),
+    ))
+}
+
+/// Validate a given object against the model of its type
+fn given_object(
+    object_type: &ObjectTypes,
+    object: serde_json::Value,
+) -> Result<NotificationObject, (StatusCode, String)> {
+    match object_type {
+        ObjectTypes::EVENT => parse_object::<OpenADREvent>(object).map(NotificationObject::Event),
+        ObjectTypes::PROGRAM => parse_object::<Program>(object).map(NotificationObject::Program),
+        ObjectTypes::REPORT => parse_object::<Report>(object).map(NotificationObject::Report),
+        ObjectTypes::SUBSCRIPTION => {
+            parse_object::<Subscription>(object).map(NotificationObject::Subscription)
+        }
+        ObjectTypes::VEN => parse_object::<Ven>(object).map(NotificationObject::Ven),
+        ObjectTypes::RESOURCE => parse_object::<Resource>(object).map(NotificationObject::Resource),
+    }
+}
+
+fn parse_object<T: DeserializeOwned>(object: serde_json::Value) -> Result<T, (StatusCode, String)> {
+    serde_json::from_value(object)
+        .map_err(|err| (StatusCode::BAD_REQUEST, format!("Invalid object: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::oauth_clients::ClientRole;
+    use crate::utils::test_helpers::{bearer, callback_server, store_subscription, test_state};
+
+    fn parameters(value: serde_json::Value) -> Json<NotificationParameters> {
+        Json(serde_json::from_value(value).unwrap())
+    }
+
+    fn query() -> Query<TriggerQuery> {
+        Query(TriggerQuery {
+            fail_on_delivery_error: true,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_trigger_notification() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "orchestrator", ClientRole::BL, None);
+        let (program_url, program_received) = callback_server(StatusCode::OK).await;
+        let (event_url, event_received) = callback_server(StatusCode::OK).await;
+        store_subscription(&state, "programs", ObjectTypes::PROGRAM, &[&program_url]);
+        store_subscription(&state, "events", ObjectTypes::EVENT, &[&event_url]);
+        let program: Program =
+            serde_json::from_value(serde_json::json!({"id": "p1", "programName": "p1"})).unwrap();
+        state.programs.insert("p1".to_string(), program);
+
+        // Only the subscriptions to the object type are notified
+        let (status, deliveries) = post_trigger_notification(
+            headers.clone(),
+            State(state.clone()),
+            query(),
+            parameters(serde_json::json!({
+                "objectType": "PROGRAM",
+                "operation": "PUT",
+                "objectID": "p1",
+            })),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(deliveries.len(), 1);
+        assert!(event_received.lock().unwrap().is_empty());
+        let notification = program_received.lock().unwrap()[0].clone();
+        assert_eq!(notification["objectType"], "PROGRAM");
+        assert_eq!(notification["operation"], "PUT");
+        assert_eq!(notification["object"]["programName"], "p1");
+
+        // A given object is sent to the subscription in the path
+        let (status, deliveries) = post_trigger_subscription_notification(
+            headers,
+            Path("events".to_string()),
+            State(state.clone()),
+            query(),
+            parameters(serde_json::json!({
+                "objectType": "EVENT",
+                "object": {"id": "e1", "programID": "1", "intervals": []},
+            })),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(deliveries.len(), 1);
+        let notification = event_received.lock().unwrap()[0].clone();
+        assert_eq!(notification["operation"], "POST");
+        assert_eq!(notification["object"]["id"], "e1");
+    }
+
+    #[tokio::test]
+    async fn test_trigger_notification_errors() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "orchestrator", ClientRole::BL, None);
+        store_subscription(&state, "reports", ObjectTypes::REPORT, &[]);
+        let status = |value: serde_json::Value| {
+            let headers = headers.clone();
+            let state = state.clone();
+            async move {
+                post_trigger_notification(headers, State(state), query(), parameters(value))
+                    .await
+                    .unwrap_err()
+                    .0
+            }
+        };
+
+        assert_eq!(
+            status(serde_json::json!({"objectType": "REPORT", "objectID": "unknown"})).await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(serde_json::json!({"objectType": "REPORT", "object": {"reportName": 1}})).await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(serde_json::json!({"objectType": "REPORT"})).await,
+            StatusCode::BAD_REQUEST
+        );
+
+        let status = post_trigger_subscription_notification(
+            headers,
+            Path("unknown".to_string()),
+            State(state.clone()),
+            query(),
+            parameters(serde_json::json!({
+                "objectType": "REPORT",
+                "object": {"programID": "1", "eventID": "1", "clientName": "ven1", "resources": []},
+            })),
+        )
+        .await
+        .unwrap_err()
+        .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
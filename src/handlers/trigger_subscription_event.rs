@@ -1,75 +1,404 @@
 use crate::utils::create_test_oadr_event::{create_test_oadr_event, EventParameters};
+use crate::utils::notifications::notification_body;
 use crate::utils::openadr_models::ObjectTypes::EVENT;
-use crate::utils::openadr_models::OpenADREvent;
+use crate::utils::openadr_models::{Notification, NotificationObject, OpenADREvent, Operation};
+use crate::utils::sessions::session_state;
+use crate::utils::webhook_delivery::{deliver, DeliveryRecord};
 use crate::AppState;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::HeaderMap;
 use axum::Json;
 use log::{debug, info};
 use reqwest::StatusCode;
+use serde::Deserialize;
 use std::sync::Arc;
 
+/// Query parameters of the subscription trigger endpoints
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerQuery {
+    /// Respond with 502 Bad Gateway if any of the deliveries to the VEN failed
+    #[serde(default)]
+    pub fail_on_delivery_error: bool,
+}
+
+/// Send a notification to the VEN according to the specified subscription
+///
+/// The notification is delivered to every object operation of the subscription that includes the object type of
+/// the notification, regardless of the subscribed operations.
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `subscription_id`: The ID of the subscription to send the notification to
+/// - `notification`: The notification to send
+///
+/// # Returns
+/// - `Result<Vec<DeliveryRecord>, (StatusCode, String)>`: The delivery records, or an error if the subscription
+///   doesn't exist
+pub async fn deliver_to_subscription(
+    state: &AppState,
+    subscription_id: &str,
+    notification: &Notification,
+) -> Result<Vec<DeliveryRecord>, (StatusCode, String)> {
+    // Check that subscription exists
+    let subscription_object_operations = match state.subscriptions.get(subscription_id) {
+        Some(subscription) => subscription.object_operations.clone(),
+        None => {
+            debug!("Subscription not found");
+            return Err((StatusCode::NOT_FOUND, "Subscription not found".to_string()));
+        }
+    };
+
+    let notification_body = notification_body(&state.secrets, notification);
+    let mut deliveries = vec![];
+
+    // Run through the object operations, and if they have the object type as an operation, send a request according to parameters
+    for object_operation in subscription_object_operations {
+        if object_operation
+            .object_type
+            .contains(&notification.object_type)
+        {
+            // Send the notification to the VEN using the object operation parameters
+            info!(
+                "Sending {:?} notification to VEN with parameters: {:?}",
+                notification.object_type, object_operation
+            );
+            let record = deliver(
+                state,
+                Some(subscription_id.to_string()),
+                &object_operation,
+                &notification_body,
+            )
+            .await;
+            deliveries.push(record);
+        }
+    }
+
+    Ok(deliveries)
+}
+
+/// Build the response of a trigger endpoint from the delivery records
+///
+/// # Parameters
+/// - `deliveries`: The records of the deliveries made by the trigger
+/// - `query`: The query parameters of the trigger request
+///
+/// # Returns
+/// - `(StatusCode, Json<Vec<DeliveryRecord>>)`: 200 with the delivery records, or 502 if any delivery failed and
+///   `failOnDeliveryError` was requested
+pub fn trigger_response(
+    deliveries: Vec<DeliveryRecord>,
+    query: &TriggerQuery,
+) -> (StatusCode, Json<Vec<DeliveryRecord>>) {
+    let failed = deliveries.iter().any(|record| !record.success);
+    if failed && query.fail_on_delivery_error {
+        return (StatusCode::BAD_GATEWAY, Json(deliveries));
+    }
+    (StatusCode::OK, Json(deliveries))
+}
+
+/// Send a notification to the VEN according to the specified subscription and build the trigger response
+async fn push_to_subscription(
+    state: &AppState,
+    subscription_id: &str,
+    notification: &Notification,
+    query: &TriggerQuery,
+) -> Result<(StatusCode, Json<Vec<DeliveryRecord>>), (StatusCode, String)> {
+    let deliveries = deliver_to_subscription(state, subscription_id, notification).await?;
+    Ok(trigger_response(deliveries, query))
+}
+
 /// Create an active event and send it to the VEN according to the specified subscription
 ///
 /// Takes event parameters in body to create a new active event and sends it to the VEN according to the subscription
 /// which is specified in the path parameter. Subscription info is used to create the request and send it to the VEN.
+/// The event is sent wrapped in an OpenADR 3.0 notification, see `notification_body` for the legacy raw event mode.
+/// Deliveries are retried according to the delivery policy and recorded in the delivery log.
+///
+/// The response lists the outcome of the delivery to each callback URL.
 ///
 /// # Parameters
 /// - `header_map`: The headers of the request
 /// - `body`: The event parameters to create the event
 /// - `subscription_id`: The ID of the subscription to send the event to
 /// - `state`: The shared memory state of the application
+/// - `query`: Optional `failOnDeliveryError` query parameter
 ///
 /// # Returns
-/// - `Result<StatusCode, (StatusCode, String)>`: The status code of the request, or an error if the request failed
+/// - `Result<(StatusCode, Json<Vec<DeliveryRecord>>), (StatusCode, String)>`: The delivery records, or an error if
+///   the request failed
 pub async fn post_trigger_subscription_event(
     header_map: HeaderMap,
     subscription_id: Path<String>,
     state: State<Arc<AppState>>,
+    query: Query<TriggerQuery>,
     body: Json<EventParameters>,
-) -> Result<StatusCode, (StatusCode, String)> {
+) -> Result<(StatusCode, Json<Vec<DeliveryRecord>>), (StatusCode, String)> {
     debug!("Triggering subscription event with parameters: {:?}", body);
-    // Auth
-    let auth_valid = crate::utils::authorizer::authorizer(&state.secrets, header_map).await;
-    if !auth_valid {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-    }
+    // Test session
+    let state = session_state(&state, &header_map)?;
 
-    // Check that subscription exists
-    let subscription_storage = state.subscriptions.clone();
-    let subscription = subscription_storage.get(&subscription_id.0);
-    let subscription_object_operations = match subscription {
-        Some(subscription) => subscription.clone().object_operations,
-        None => {
-            debug!("Subscription not found");
-            return Err((StatusCode::NOT_FOUND, "Subscription not found".to_string()));
-        }
-    };
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&state, header_map).await?;
 
     // Validate parameters
-    if body.length < 1 || body.limit_kw < 1 || body.minutes_in_future < 1 {
+    if !body.is_valid() {
         return Err((StatusCode::BAD_REQUEST, "Invalid parameters".to_string()));
     }
 
     // Create a new event based on the event parameters
     let oadr_event: OpenADREvent = create_test_oadr_event(body.0).await;
+    let notification = Notification {
+        object_type: EVENT,
+        operation: Operation::POST,
+        targets: oadr_event.targets.clone(),
+        object: NotificationObject::Event(oadr_event),
+    };
+    push_to_subscription(&state, &subscription_id.0, &notification, &query).await
+}
 
-    // Run through the object operations, and if they have an event type as an operation, send a request according to parameters
-    for object_operation in subscription_object_operations {
-        // Check if the object operations has events as an operation
-        if object_operation.object_type.contains(&EVENT) {
-            // Send the event to the VEN using the object operation parameters
-            info!(
-                "Sending event to VEN with parameters: {:?}",
-                object_operation
-            );
-            let _request = reqwest::Client::new()
-                .post(&object_operation.callback_url)
-                .bearer_auth(&object_operation.bearer_token)
-                .json(&oadr_event)
-                .send()
-                .await;
+/// Send a stored event to the VEN according to the specified subscription
+///
+/// The event is looked up from the event storage by its ID and sent as is, so that the VEN receives the exact
+/// same event via push and via polling. Can be used to test that the VEN deduplicates events.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `path`: The ID of the subscription and the ID of the stored event as path parameters
+/// - `state`: The shared memory state of the application
+/// - `query`: Optional `failOnDeliveryError` query parameter
+///
+/// # Returns
+/// - `Result<(StatusCode, Json<Vec<DeliveryRecord>>), (StatusCode, String)>`: The delivery records, or an error if
+///   the request failed
+pub async fn post_trigger_subscription_stored_event(
+    header_map: HeaderMap,
+    path: Path<(String, String)>,
+    state: State<Arc<AppState>>,
+    query: Query<TriggerQuery>,
+) -> Result<(StatusCode, Json<Vec<DeliveryRecord>>), (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&state, header_map).await?;
+
+    let (subscription_id, event_id) = path.0;
+    let oadr_event = {
+        let storage = state.event_storage.read().await;
+        match storage
+            .iter()
+            .find(|event| event.id.as_ref() == Some(&event_id))
+        {
+            Some(event) => event.clone(),
+            None => return Err((StatusCode::NOT_FOUND, "Event not found".to_string())),
         }
+    };
+
+    let notification = Notification {
+        object_type: EVENT,
+        operation: Operation::POST,
+        targets: oadr_event.targets.clone(),
+        object: NotificationObject::Event(oadr_event),
+    };
+    push_to_subscription(&state, &subscription_id, &notification, &query).await
+}
+
+/// Send the given event to the VEN according to the specified subscription
+///
+/// The event in the body is sent as is and is NOT stored. Can be used to push an event with exactly known contents,
+/// eg. a copy of a stored event with the same ID.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `subscription_id`: The ID of the subscription to send the event to
+/// - `state`: The shared memory state of the application
+/// - `query`: Optional `failOnDeliveryError` query parameter
+/// - `body`: The event to send
+///
+/// # Returns
+/// - `Result<(StatusCode, Json<Vec<DeliveryRecord>>), (StatusCode, String)>`: The delivery records, or an error if
+///   the request failed
+pub async fn post_trigger_subscription_event_body(
+    header_map: HeaderMap,
+    subscription_id: Path<String>,
+    state: State<Arc<AppState>>,
+    query: Query<TriggerQuery>,
+    body: Json<OpenADREvent>,
+) -> Result<(StatusCode, Json<Vec<DeliveryRecord>>), (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Admin auth
+    crate::utils::authorizer::admin_authorizer(&state, header_map).await?;
+
+    let oadr_event = body.0;
+    let notification = Notification {
+        object_type: EVENT,
+        operation: Operation::POST,
+        targets: oadr_event.targets.clone(),
+        object: NotificationObject::Event(oadr_event),
+    };
+    push_to_subscription(&state, &subscription_id.0, &notification, &query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::init_storage::dummy_event_to_storage;
+    use crate::utils::oauth_clients::ClientRole;
+    use crate::utils::test_helpers::{
+        bearer, callback_server, store_subscription, test_state, UNREACHABLE_CALLBACK_URL,
+    };
+
+    fn event_parameters() -> Json<EventParameters> {
+        Json(
+            serde_json::from_value(serde_json::json!({
+                "eventName": "test",
+                "oadrResourceName": "resource",
+                "length": 60,
+                "limitKw": 100,
+                "minutesInFuture": 5,
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_trigger_subscription_event_outcomes() {
+        let state = test_state(&[("WEBHOOK_MAX_ATTEMPTS", "1")]).await;
+        let headers = bearer(&state, "orchestrator", ClientRole::BL, None);
+        let (callback_url, received) = callback_server(StatusCode::OK).await;
+        store_subscription(
+            &state,
+            "sub1",
+            EVENT,
+            &[&callback_url, UNREACHABLE_CALLBACK_URL],
+        );
+        let trigger = |fail_on_delivery_error| {
+            post_trigger_subscription_event(
+                headers.clone(),
+                Path("sub1".to_string()),
+                State(state.clone()),
+                Query(TriggerQuery {
+                    fail_on_delivery_error,
+                }),
+                event_parameters(),
+            )
+        };
+
+        // The outcome of every callback is reported
+        let (status, deliveries) = trigger(false).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        let outcomes = serde_json::to_value(&deliveries.0).unwrap();
+        assert_eq!(outcomes[0]["subscriptionID"], "sub1");
+        assert_eq!(outcomes[0]["callbackUrl"], callback_url.as_str());
+        assert_eq!(outcomes[0]["success"], true);
+        assert_eq!(outcomes[0]["responseStatus"], 200);
+        assert_eq!(outcomes[0]["attempts"], 1);
+        assert_eq!(outcomes[1]["success"], false);
+        assert!(outcomes[1]["responseStatus"].is_null());
+        assert!(outcomes[1]["error"].is_string());
+        assert_eq!(received.lock().unwrap().len(), 1);
+        assert_eq!(outcomes[0]["payload"], received.lock().unwrap()[0].clone());
+
+        // Failed deliveries fail the request on demand
+        let (status, deliveries) = trigger(true).await.unwrap();
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(deliveries.len(), 2);
+        assert_eq!(state.delivery_log.read().await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_trigger_subscription_event_not_found() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "orchestrator", ClientRole::BL, None);
+
+        let status = post_trigger_subscription_event(
+            headers,
+            Path("unknown".to_string()),
+            State(state.clone()),
+            Query(TriggerQuery {
+                fail_on_delivery_error: false,
+            }),
+            event_parameters(),
+        )
+        .await
+        .unwrap_err()
+        .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(state.delivery_log.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_trigger_subscription_stored_event() {
+        let state = test_state(&[]).await;
+        dummy_event_to_storage(&state).await;
+        let headers = bearer(&state, "orchestrator", ClientRole::BL, None);
+        let (callback_url, received) = callback_server(StatusCode::OK).await;
+        store_subscription(&state, "sub1", EVENT, &[&callback_url]);
+        let query = || {
+            Query(TriggerQuery {
+                fail_on_delivery_error: true,
+            })
+        };
+
+        // The stored event is pushed as is
+        let (status, deliveries) = post_trigger_subscription_stored_event(
+            headers.clone(),
+            Path(("sub1".to_string(), "dummyTest".to_string())),
+            State(state.clone()),
+            query(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(deliveries.len(), 1);
+        let stored = serde_json::to_value(&state.event_storage.read().await[0]).unwrap();
+        assert_eq!(received.lock().unwrap()[0]["object"], stored);
+
+        // Unknown events and subscriptions aren't found
+        for (subscription_id, event_id) in [("sub1", "unknown"), ("unknown", "dummyTest")] {
+            let status = post_trigger_subscription_stored_event(
+                headers.clone(),
+                Path((subscription_id.to_string(), event_id.to_string())),
+                State(state.clone()),
+                query(),
+            )
+            .await
+            .unwrap_err()
+            .0;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_trigger_subscription_event_body() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "orchestrator", ClientRole::BL, None);
+        let (callback_url, received) = callback_server(StatusCode::OK).await;
+        store_subscription(&state, "sub1", EVENT, &[&callback_url]);
+        let event = serde_json::json!({
+            "id": "given",
+            "programID": "1",
+            "intervals": [],
+        });
+
+        // The given event is pushed as is and isn't stored
+        let (status, _) = post_trigger_subscription_event_body(
+            headers,
+            Path("sub1".to_string()),
+            State(state.clone()),
+            Query(TriggerQuery {
+                fail_on_delivery_error: true,
+            }),
+            Json(serde_json::from_value(event.clone()).unwrap()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(received.lock().unwrap()[0]["object"], event);
+        assert!(state.event_storage.read().await.is_empty());
     }
-    Ok(StatusCode::OK)
 }
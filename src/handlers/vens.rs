@@ -0,0 +1,388 @@
+use crate::utils::authorizer::authorize_scope;
+use crate::utils::client_access::{can_access, remove_owner, set_owner};
+use crate::utils::iso8601::Timestamp;
+use crate::utils::openadr_models::{ObjectTypes, Ven};
+use crate::utils::query_filters::QueryFilters;
+use crate::utils::sessions::session_state;
+use crate::utils::tokens::Scope;
+use crate::AppState;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use log::debug;
+use std::sync::Arc;
+
+/// Get all registered VENs
+///
+/// Supports the OpenADR 3.0 query parameters `venName`, `targetType`, `targetValues`, `skip` and `limit`. Clients
+/// without the read_all scope only get the VENs they registered.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `query`: The query string key value pairs of the request
+///
+/// # Returns
+/// - `Result<Json<Vec<Ven>>, (StatusCode, String)>`: The list of VENs, or an error if the request failed
+pub async fn get_vens(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    query: Query<Vec<(String, String)>>,
+) -> Result<Json<Vec<Ven>>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::ReadTargets).await?;
+
+    let filters =
+        QueryFilters::from_pairs(query.0).map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+
+    let vens: Vec<Ven> = state
+        .vens
+        .iter()
+        .filter(|ven| {
+            can_access(&state, &token, ObjectTypes::VEN, ven.key())
+                && filters.matches_ven_name(&ven.ven_name)
+                && filters.matches_targets(&ven.targets)
+        })
+        .map(|entry| entry.value().clone())
+        .collect();
+    let vens = filters.paginate(vens);
+
+    debug!("Returning VENs: {:?}", vens);
+    Ok(Json(vens))
+}
+
+/// Get a specific VEN
+///
+/// VENs registered by other clients can only be read with the read_all scope.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `ven_id`: The ID of the VEN to get as a path parameter
+///
+/// # Returns
+/// - `Result<Json<Ven>, (StatusCode, String)>`: The VEN, or an error if the request failed
+pub async fn get_ven(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    ven_id: Path<String>,
+) -> Result<Json<Ven>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::ReadTargets).await?;
+
+    debug!("Getting VEN: {:?}", ven_id.0);
+    let ven = state.vens.get(&ven_id.0);
+    match ven {
+        Some(ven) if can_access(&state, &token, ObjectTypes::VEN, &ven_id.0) => {
+            Ok(Json(ven.clone()))
+        }
+        _ => Err((StatusCode::NOT_FOUND, "VEN not found".to_string())),
+    }
+}
+
+/// Register a new VEN
+///
+/// The VTN provisions the ID, creation and modification timestamps of the VEN. Any values for these fields
+/// in the request body are ignored. Like production VTNs, VEN names have to be unique. The VEN is registered to the
+/// client, events targeted to the VEN or its resources are visible to the client.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `ven`: The VEN object to register
+///
+/// # Returns
+/// - `Result<(StatusCode, Json<Ven>), (StatusCode, String)>`: The registered VEN, or an error if the request failed
+pub async fn post_ven(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    ven: Json<Ven>,
+) -> Result<(StatusCode, Json<Ven>), (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::WriteVens).await?;
+
+    let mut ven = ven.0;
+    if ven_name_taken(&state, &ven.ven_name, None) {
+        return Err((StatusCode::CONFLICT, "VEN name already exists".to_string()));
+    }
+
+    // Provision the VTN controlled fields
+    let time_now = Timestamp::now();
+    ven.id = Some(uuid::Uuid::new_v4().to_string());
+    ven.created_date_time = Some(time_now);
+    ven.modification_date_time = Some(time_now);
+    ven.object_type = Some(ObjectTypes::VEN);
+
+    state.vens.insert(ven.id.clone().unwrap(), ven.clone());
+    set_owner(&state, ObjectTypes::VEN, ven.id.as_ref().unwrap(), &token);
+
+    debug!("VEN registered: {:?}", ven);
+    Ok((StatusCode::CREATED, Json(ven)))
+}
+
+/// Update an existing VEN
+///
+/// The ID and creation timestamp of the stored VEN are kept, the modification timestamp is updated. VENs registered by
+/// other clients can only be updated with the read_all scope.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `ven_id`: The ID of the VEN to update as a path parameter
+/// - `ven`: The updated VEN object
+///
+/// # Returns
+/// - `Result<Json<Ven>, (StatusCode, String)>`: The updated VEN, or an error if the request failed
+pub async fn put_ven(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    ven_id: Path<String>,
+    ven: Json<Ven>,
+) -> Result<Json<Ven>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::WriteVens).await?;
+
+    let created_date_time = match state.vens.get(&ven_id.0) {
+        Some(existing) if can_access(&state, &token, ObjectTypes::VEN, &ven_id.0) => {
+            existing.created_date_time
+        }
+        _ => return Err((StatusCode::NOT_FOUND, "VEN not found".to_string())),
+    };
+
+    let mut ven = ven.0;
+    if ven_name_taken(&state, &ven.ven_name, Some(&ven_id.0)) {
+        return Err((StatusCode::CONFLICT, "VEN name already exists".to_string()));
+    }
+
+    ven.id = Some(ven_id.0.clone());
+    ven.created_date_time = created_date_time;
+    ven.modification_date_time = Some(Timestamp::now());
+    ven.object_type = Some(ObjectTypes::VEN);
+
+    state.vens.insert(ven_id.0, ven.clone());
+
+    debug!("VEN updated: {:?}", ven);
+    Ok(Json(ven))
+}
+
+/// Delete a specific VEN
+///
+/// The resources of the VEN are deleted as well. VENs registered by other clients can only be deleted with the read_all
+/// scope.
+///
+/// # Parameters
+/// - `header_map`: The headers of the request
+/// - `state`: The shared memory state of the application
+/// - `ven_id`: The ID of the VEN to delete as a path parameter
+///
+/// # Returns
+/// - `Result<Json<Ven>, (StatusCode, String)>`: The deleted VEN, or an error if the request failed
+pub async fn delete_ven(
+    header_map: HeaderMap,
+    state: State<Arc<AppState>>,
+    ven_id: Path<String>,
+) -> Result<Json<Ven>, (StatusCode, String)> {
+    // Test session
+    let state = session_state(&state, &header_map)?;
+
+    // Auth
+    let token = authorize_scope(&state, header_map, Scope::WriteVens).await?;
+
+    debug!("Deleting VEN: {:?}", ven_id.0);
+    match state.vens.remove_if(&ven_id.0, |id, _ven| {
+        can_access(&state, &token, ObjectTypes::VEN, id)
+    }) {
+        Some((_id, ven)) => {
+            state.resources.retain(|resource_id, resource| {
+                let of_ven = resource.ven_id.as_ref() == Some(&ven_id.0);
+                if of_ven {
+                    remove_owner(&state, ObjectTypes::RESOURCE, resource_id);
+                }
+                !of_ven
+            });
+            remove_owner(&state, ObjectTypes::VEN, &ven_id.0);
+            Ok(Json(ven))
+        }
+        None => Err((StatusCode::NOT_FOUND, "VEN not found".to_string())),
+    }
+}
+
+/// Check whether a VEN name is already used by another VEN than the one being updated
+fn ven_name_taken(state: &AppState, ven_name: &str, ven_id: Option<&String>) -> bool {
+    state
+        .vens
+        .iter()
+        .any(|ven| ven.ven_name == ven_name && Some(ven.key()) != ven_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::oauth_clients::ClientRole;
+    use crate::utils::openadr_models::Resource;
+    use crate::utils::test_helpers::{bearer, test_state};
+
+    fn ven(ven_name: &str) -> Json<Ven> {
+        Json(
+            serde_json::from_value(serde_json::json!({
+                "id": "client-id",
+                "createdDateTime": "2020-01-01T00:00:00Z",
+                "venName": ven_name,
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_ven_registration() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "ven1", ClientRole::VEN, None);
+
+        // The VTN provisions the ID and timestamps
+        let (status, registered) = post_ven(headers.clone(), State(state.clone()), ven("ven1"))
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        let ven_id = registered.id.clone().unwrap();
+        assert_ne!(ven_id, "client-id");
+        assert!(registered.created_date_time > Some("2020-01-01T00:00:00Z".parse().unwrap()));
+        assert_eq!(
+            registered.created_date_time,
+            registered.modification_date_time
+        );
+        assert_eq!(registered.object_type, Some(ObjectTypes::VEN));
+
+        // VEN names are unique
+        let status = post_ven(headers.clone(), State(state.clone()), ven("ven1"))
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // Updates keep the ID and creation timestamp
+        let updated = put_ven(
+            headers.clone(),
+            State(state.clone()),
+            Path(ven_id.clone()),
+            ven("ven1-renamed"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(updated.id, Some(ven_id.clone()));
+        assert_eq!(updated.created_date_time, registered.created_date_time);
+        let vens = get_vens(
+            headers.clone(),
+            State(state.clone()),
+            Query(vec![("venName".to_string(), "ven1-renamed".to_string())]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(vens.len(), 1);
+
+        // Deleting the VEN deletes its resources
+        let resource: Resource = serde_json::from_value(serde_json::json!({
+            "resourceName": "resource1",
+            "venID": ven_id,
+        }))
+        .unwrap();
+        state.resources.insert("resource1".to_string(), resource);
+        let deleted = delete_ven(headers, State(state.clone()), Path(ven_id))
+            .await
+            .unwrap();
+        assert_eq!(deleted.ven_name, "ven1-renamed");
+        assert!(state.vens.is_empty());
+        assert!(state.resources.is_empty());
+        assert!(state.owners.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ven_not_found() {
+        let state = test_state(&[]).await;
+        let headers = bearer(&state, "ven1", ClientRole::VEN, None);
+        let unknown = || Path("unknown".to_string());
+
+        let status = get_ven(headers.clone(), State(state.clone()), unknown())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = put_ven(
+            headers.clone(),
+            State(state.clone()),
+            unknown(),
+            ven("ven1"),
+        )
+        .await
+        .unwrap_err()
+        .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = delete_ven(headers, State(state.clone()), unknown())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(state.vens.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ven_ownership() {
+        let state = test_state(&[]).await;
+        let ven1 = bearer(
+            &state,
+            "ven1",
+            ClientRole::VEN,
+            Some("read_targets write_vens"),
+        );
+        let ven2 = bearer(
+            &state,
+            "ven2",
+            ClientRole::VEN,
+            Some("read_targets write_vens"),
+        );
+        let bl = bearer(&state, "bl", ClientRole::BL, Some("read_all write_vens"));
+        let (_, registered) = post_ven(ven1.clone(), State(state.clone()), ven("ven1"))
+            .await
+            .unwrap();
+        let ven_id = || Path(registered.id.clone().unwrap());
+
+        // Other clients don't see the VEN
+        let vens = |headers: HeaderMap| get_vens(headers, State(state.clone()), Query(vec![]));
+        assert_eq!(vens(ven1.clone()).await.unwrap().len(), 1);
+        assert!(vens(ven2.clone()).await.unwrap().is_empty());
+        assert_eq!(vens(bl.clone()).await.unwrap().len(), 1);
+        let status = get_ven(ven2.clone(), State(state.clone()), ven_id())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = put_ven(ven2.clone(), State(state.clone()), ven_id(), ven("ven2"))
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = delete_ven(ven2, State(state.clone()), ven_id())
+            .await
+            .unwrap_err()
+            .0;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(state.vens.len(), 1);
+
+        // read_all clients can manage every VEN
+        let deleted = delete_ven(bl, State(state.clone()), ven_id())
+            .await
+            .unwrap();
+        assert_eq!(deleted.ven_name, "ven1");
+    }
+}
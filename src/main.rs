@@ -1,7 +1,15 @@
 use crate::utils::init_storage::dummy_event_to_storage;
-use crate::utils::openadr_models::{OpenADREvent, Subscription};
+use crate::utils::oauth_clients::OAuthClient;
+use crate::utils::openadr_models::{
+    ObjectTypes, OpenADREvent, Program, Report, Resource, Subscription, Ven,
+};
+use crate::utils::sessions::TestSession;
+use crate::utils::tokens::IssuedToken;
+use crate::utils::webhook_delivery::{DeliveryPolicy, DeliveryRecord};
 use dashmap::DashMap;
 use shuttle_runtime::SecretStore;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
 use tokio::sync::RwLock;
 
 mod handlers;
@@ -14,6 +22,34 @@ pub struct AppState {
     pub event_storage: RwLock<Vec<OpenADREvent>>,
     /// Subscriptions storage map. Key is Subscription id, content is the subscription object itself.
     pub subscriptions: DashMap<String, Subscription>,
+    /// Programs storage map. Key is Program id, content is the program object itself.
+    pub programs: DashMap<String, Program>,
+    /// Reports storage map. Key is Report id, content is the report object itself.
+    pub reports: DashMap<String, Report>,
+    /// VENs storage map. Key is VEN id, content is the VEN object itself.
+    pub vens: DashMap<String, Ven>,
+    /// Resources storage map. Key is Resource id, content is the resource object itself including the VEN id.
+    pub resources: DashMap<String, Resource>,
+    /// Delivery log of the webhook deliveries to subscription callback URLs, oldest first. Capped by the delivery policy.
+    pub delivery_log: RwLock<VecDeque<DeliveryRecord>>,
+    /// Timeout and retry policy of the webhook deliveries
+    pub delivery_policy: DeliveryPolicy,
+    /// HTTP client shared by the webhook deliveries
+    pub http_client: reqwest::Client,
+    /// Owners of the objects created by the OpenADR clients. Key is the object type and id, content is the client id.
+    pub owners: DashMap<(ObjectTypes, String), String>,
+    /// OAuth clients allowed to fetch tokens. Key is the client id, content is the client credentials, scopes and role.
+    pub clients: HashMap<String, OAuthClient>,
+    /// Lifetime of the access tokens issued by the auth endpoint
+    pub token_lifetime: chrono::Duration,
+    /// Access tokens issued by the auth endpoint. Key is the token, content is the client, expiry and scope of the token.
+    /// Shared by the test sessions, so that the same token is valid in every session.
+    pub tokens: Arc<DashMap<String, IssuedToken>>,
+    /// Test sessions, only used in the application state. Key is the session id, content is the isolated state of the
+    /// session.
+    pub sessions: DashMap<String, TestSession>,
+    /// Serializes the creation of test sessions, so that a client can't be bound to two sessions created concurrently
+    pub session_lock: std::sync::Mutex<()>,
     /// Secrets store - Used to access the application secrets defined in Secrets.toml at runtime
     pub secrets: SecretStore,
 }
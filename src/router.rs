@@ -1,13 +1,29 @@
 use crate::handlers::auth::post_auth;
 use crate::handlers::clear_events_list::post_clear_events;
-use crate::handlers::events::get_events;
+use crate::handlers::deliveries::{delete_deliveries, get_deliveries};
+use crate::handlers::events::{delete_event, get_event, get_events, post_event, put_event};
 use crate::handlers::generate_initial_subscription::post_generate_initial_subscription;
 use crate::handlers::generate_polled_event::post_generate_polled_event;
 use crate::handlers::ping::get_ping;
+use crate::handlers::programs::{
+    delete_program, get_program, get_programs, post_program, put_program,
+};
+use crate::handlers::reports::{delete_report, get_report, get_reports, post_report, put_report};
+use crate::handlers::resources::{
+    delete_resource, get_resource, get_resources, post_resource, put_resource,
+};
+use crate::handlers::sessions::{delete_session, get_sessions, post_session};
 use crate::handlers::subscription::{
     delete_subscription, get_subscription, get_subscriptions, post_subscription, put_subscription,
 };
-use crate::handlers::trigger_subscription_event::post_trigger_subscription_event;
+use crate::handlers::trigger_notification::{
+    post_trigger_notification, post_trigger_subscription_notification,
+};
+use crate::handlers::trigger_subscription_event::{
+    post_trigger_subscription_event, post_trigger_subscription_event_body,
+    post_trigger_subscription_stored_event,
+};
+use crate::handlers::vens::{delete_ven, get_ven, get_vens, post_ven, put_ven};
 use crate::AppState;
 use axum::routing::{delete, put};
 use axum::{routing::get, routing::post, Router};
@@ -22,6 +38,10 @@ pub fn build_router(shared_memory: Arc<AppState>) -> Router {
         .route("/ping", get(get_ping))
         .route("/auth", post(post_auth))
         .route("/events", get(get_events))
+        .route("/events", post(post_event))
+        .route("/events/:id", get(get_event))
+        .route("/events/:id", put(put_event))
+        .route("/events/:id", delete(delete_event))
         .route("/admin/trigger/event", post(post_generate_polled_event))
         .route("/admin/trigger/clear_events", post(post_clear_events))
         .route("/subscription", post(post_subscription))
@@ -29,13 +49,57 @@ pub fn build_router(shared_memory: Arc<AppState>) -> Router {
         .route("/subscription", get(get_subscriptions))
         .route("/subscription/:id", delete(delete_subscription))
         .route("/subscription/:id", put(put_subscription))
+        .route("/programs", get(get_programs))
+        .route("/programs", post(post_program))
+        .route("/programs/:id", get(get_program))
+        .route("/programs/:id", put(put_program))
+        .route("/programs/:id", delete(delete_program))
+        .route("/reports", get(get_reports))
+        .route("/reports", post(post_report))
+        .route("/reports/:id", get(get_report))
+        .route("/reports/:id", put(put_report))
+        .route("/reports/:id", delete(delete_report))
+        .route("/vens", get(get_vens))
+        .route("/vens", post(post_ven))
+        .route("/vens/:ven_id", get(get_ven))
+        .route("/vens/:ven_id", put(put_ven))
+        .route("/vens/:ven_id", delete(delete_ven))
+        .route("/vens/:ven_id/resources", get(get_resources))
+        .route("/vens/:ven_id/resources", post(post_resource))
+        .route("/vens/:ven_id/resources/:resource_id", get(get_resource))
+        .route("/vens/:ven_id/resources/:resource_id", put(put_resource))
+        .route(
+            "/vens/:ven_id/resources/:resource_id",
+            delete(delete_resource),
+        )
         .route(
             "/admin/trigger/subscription/:id",
             post(post_trigger_subscription_event),
         )
+        .route(
+            "/admin/trigger/subscription/:id/event",
+            post(post_trigger_subscription_event_body),
+        )
+        .route(
+            "/admin/trigger/subscription/:id/event/:event_id",
+            post(post_trigger_subscription_stored_event),
+        )
+        .route(
+            "/admin/trigger/subscription/:id/notification",
+            post(post_trigger_subscription_notification),
+        )
+        .route(
+            "/admin/trigger/notification",
+            post(post_trigger_notification),
+        )
         .route(
             "/admin/trigger/initial_subscription",
             post(post_generate_initial_subscription),
         )
+        .route("/admin/deliveries", get(get_deliveries))
+        .route("/admin/deliveries", delete(delete_deliveries))
+        .route("/admin/sessions", get(get_sessions))
+        .route("/admin/sessions", post(post_session))
+        .route("/admin/sessions/:id", delete(delete_session))
         .with_state(shared_memory)
 }
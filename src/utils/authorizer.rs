@@ -1,67 +1,341 @@
-use axum::http::HeaderMap;
-use shuttle_runtime::SecretStore;
-
-/// Dummy authorizer, checks for a valid token auth header, which is a static string fetched from secrets
-pub async fn authorizer(secret_store: &SecretStore, header_map: HeaderMap) -> bool {
-    // Extract auth headers and validate them
-    let valid_header = format!(
-        "Bearer {}",
-        secret_store
-            .get("DUMMY_TOKEN")
-            .expect("DUMMY_TOKEN variable not set!")
-    );
-    let auth_header = header_map.get("Authorization");
-
-    // check if header is present and matches our token
-    match auth_header {
-        Some(header) => {
-            let header = header.to_str().unwrap();
-            if header != valid_header {
-                return false;
-            }
-            true
+use crate::utils::oauth_clients::ClientRole;
+use crate::utils::tokens::{validate_token, IssuedToken, Scope};
+use crate::AppState;
+use axum::http::{HeaderMap, StatusCode};
+use log::debug;
+
+/// Scope authorizer, checks for a valid bearer token auth header granting the required scope
+///
+/// Valid tokens are the unexpired tokens issued by the auth endpoint, or the static `DUMMY_TOKEN` secret when the
+/// `STATIC_TOKEN` compatibility switch is enabled.
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `header_map`: The headers of the request
+/// - `scope`: The scope required by the endpoint
+///
+/// # Returns
+/// - `Result<IssuedToken, (StatusCode, String)>`: The token of the calling client, or 401 Unauthorized if the token is
+///   missing or invalid and 403 Forbidden if the token lacks the scope
+pub async fn authorize_scope(
+    state: &AppState,
+    header_map: HeaderMap,
+    scope: Scope,
+) -> Result<IssuedToken, (StatusCode, String)> {
+    let token = match authenticated_client(state, &header_map) {
+        Some(token) => token,
+        None => {
+            debug!("Invalid auth header");
+            return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
+        }
+    };
+
+    if !token.has_scope(scope) {
+        debug!(
+            "Client {} lacks scope {}, granted: {:?}",
+            token.client_id,
+            scope.as_str(),
+            token.scope
+        );
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("Missing scope: {}", scope.as_str()),
+        ));
+    }
+    Ok(token)
+}
+
+/// Admin authorizer, checks for a valid admin bearer token auth header
+///
+/// Admin credentials are the optional `ADMIN_TOKEN` secret, or a token issued to a client with the BL role. VEN tokens,
+/// including the static `DUMMY_TOKEN`, are not accepted.
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `header_map`: The headers of the request
+///
+/// # Returns
+/// - `Result<(), (StatusCode, String)>`: Ok for admin credentials, 401 Unauthorized if the token is missing or invalid
+///   and 403 Forbidden if the token isn't an admin token
+pub async fn admin_authorizer(
+    state: &AppState,
+    header_map: HeaderMap,
+) -> Result<(), (StatusCode, String)> {
+    let token = match bearer_token(&header_map) {
+        Some(token) => token,
+        None => {
+            debug!("No admin auth header");
+            return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
+        }
+    };
+
+    if has_admin_token(state, &header_map) {
+        return Ok(());
+    }
+
+    match validate_token(state, token) {
+        Some(issued) if issued.role == ClientRole::BL => Ok(()),
+        Some(issued) => {
+            debug!("Client {} is not an admin client", issued.client_id);
+            Err((
+                StatusCode::FORBIDDEN,
+                "Admin credentials required".to_string(),
+            ))
+        }
+        None => {
+            debug!("Invalid admin auth header");
+            Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()))
         }
-        None => false,
     }
 }
 
+/// Get the token of the calling client from the bearer token auth header
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `header_map`: The headers of the request
+///
+/// # Returns
+/// - `Option<IssuedToken>`: The valid token including the client identity, or None if the token is missing or invalid
+pub fn authenticated_client(state: &AppState, header_map: &HeaderMap) -> Option<IssuedToken> {
+    validate_token(state, bearer_token(header_map)?)
+}
+
+/// Check whether the request has valid credentials, either a valid token or the admin token
+///
+/// Doesn't check the scope or role, only used to avoid revealing the test sessions to unauthenticated requests.
+pub fn has_credentials(state: &AppState, header_map: &HeaderMap) -> bool {
+    has_admin_token(state, header_map) || authenticated_client(state, header_map).is_some()
+}
+
+/// Check whether the request carries the `ADMIN_TOKEN` secret as its bearer token
+pub fn has_admin_token(state: &AppState, header_map: &HeaderMap) -> bool {
+    bearer_token(header_map).is_some_and(|token| {
+        state
+            .secrets
+            .get("ADMIN_TOKEN")
+            .is_some_and(|admin_token| admin_token == token)
+    })
+}
+
+/// Extract the bearer token from the auth header
+fn bearer_token(header_map: &HeaderMap) -> Option<&str> {
+    header_map
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::init_storage::init_storage;
+    use crate::utils::oauth_clients::{ClientRole, OAuthClient};
+    use crate::utils::tokens::issue_token;
     use axum::http::HeaderValue;
     use shuttle_common::Secret;
+    use shuttle_runtime::SecretStore;
     use std::collections::BTreeMap;
     use std::env;
 
+    fn bearer(token: &str) -> HeaderMap {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(
+            "Authorization",
+            HeaderValue::from_str(format!("Bearer {}", token).as_str()).unwrap(),
+        );
+        header_map
+    }
+
+    fn client() -> OAuthClient {
+        OAuthClient {
+            client_id: "ven1".to_string(),
+            client_secret: "secret".to_string(),
+            scopes: None,
+            role: ClientRole::VEN,
+        }
+    }
+
     #[tokio::test]
     async fn test_authorizer() {
         // Loading secrets with dotenvy to get the dummy token for testing
-        dotenvy::from_filename("Secrets.test.toml").expect("Failed to load secrets.toml for unit tests");
+        dotenvy::from_filename("Secrets.test.toml")
+            .expect("Failed to load secrets.toml for unit tests");
         let dummy_token = env::var("DUMMY_TOKEN").expect("couldn't load dummy token variable");
 
         // Build dummy secrets store for testing
         let mut secrets_tree: BTreeMap<String, Secret<String>> = BTreeMap::new();
         secrets_tree.insert("DUMMY_TOKEN".to_string(), Secret::new(dummy_token.clone()));
-        let secrets = SecretStore::new(secrets_tree);
+        let state = init_storage(SecretStore::new(secrets_tree)).await;
 
-        // Test with valid token
-        let mut header_map = HeaderMap::new();
-        header_map.insert(
-            "Authorization",
-            HeaderValue::from_str(format!("Bearer {}", dummy_token).as_str()).unwrap(),
+        // Test with issued tokens, every token is distinct
+        let token = issue_token(&state, &client(), Some("read_all".to_string()));
+        assert_eq!(token.token_type, "Bearer");
+        assert_eq!(token.expires_in, 3600);
+        assert_ne!(
+            token.access_token,
+            issue_token(&state, &client(), None).access_token
         );
-        assert_eq!(authorizer(&secrets, header_map).await, true);
+        assert!(authenticated_client(&state, &bearer(&token.access_token)).is_some());
 
-        // Test with invalid token
-        let mut header_map = HeaderMap::new();
-        header_map.insert(
-            "Authorization",
-            HeaderValue::from_str("Bearer test_dummy2").unwrap(),
+        // The token carries the client identity
+        let issued = authenticated_client(&state, &bearer(&token.access_token)).unwrap();
+        assert_eq!(issued.client_id, "ven1");
+        assert_eq!(issued.role, ClientRole::VEN);
+        assert_eq!(issued.scope, Some("read_all".to_string()));
+
+        // Test with expired token
+        state.tokens.insert(
+            "expired".to_string(),
+            IssuedToken {
+                client_id: "ven1".to_string(),
+                role: ClientRole::VEN,
+                expires_at: chrono::Utc::now() - chrono::Duration::seconds(1),
+                scope: None,
+            },
         );
-        assert_eq!(authorizer(&secrets, header_map).await, false);
+        assert!(authenticated_client(&state, &bearer("expired")).is_none());
+        assert!(!state.tokens.contains_key("expired"));
+
+        // Test with the static token while the compatibility switch is off
+        assert!(authenticated_client(&state, &bearer(&dummy_token)).is_none());
+
+        // Test with invalid token
+        assert!(authenticated_client(&state, &bearer("test_dummy2")).is_none());
 
         // Test with no token
         let header_map = HeaderMap::new();
-        assert_eq!(authorizer(&secrets, header_map).await, false);
+        assert!(authenticated_client(&state, &header_map).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_authorize_scope() {
+        let state = init_storage(SecretStore::new(BTreeMap::new())).await;
+        let status = |result: Result<IssuedToken, (StatusCode, String)>| {
+            result.map(|_| ()).map_err(|err| err.0)
+        };
+
+        let ven_token = issue_token(
+            &state,
+            &client(),
+            Some("read_targets write_reports".to_string()),
+        );
+        let headers = bearer(&ven_token.access_token);
+        assert_eq!(
+            status(authorize_scope(&state, headers.clone(), Scope::ReadTargets).await),
+            Ok(())
+        );
+        assert_eq!(
+            status(authorize_scope(&state, headers.clone(), Scope::WriteReports).await),
+            Ok(())
+        );
+        assert_eq!(
+            status(authorize_scope(&state, headers.clone(), Scope::WriteEvents).await),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            status(authorize_scope(&state, headers, Scope::ReadAll).await),
+            Err(StatusCode::FORBIDDEN)
+        );
+
+        // read_all grants read_targets
+        let bl_token = issue_token(&state, &client(), Some("read_all".to_string()));
+        assert_eq!(
+            status(
+                authorize_scope(&state, bearer(&bl_token.access_token), Scope::ReadTargets).await
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            status(authorize_scope(&state, bearer("test_dummy2"), Scope::ReadTargets).await),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_authorizer_static_token() {
+        let mut secrets_tree: BTreeMap<String, Secret<String>> = BTreeMap::new();
+        secrets_tree.insert(
+            "DUMMY_TOKEN".to_string(),
+            Secret::new("dummy_token".to_string()),
+        );
+        secrets_tree.insert("STATIC_TOKEN".to_string(), Secret::new("true".to_string()));
+        let state = init_storage(SecretStore::new(secrets_tree)).await;
+
+        // The static token is issued and accepted without being tracked
+        let token = issue_token(&state, &client(), None);
+        assert_eq!(token.access_token, "dummy_token");
+        assert!(state.tokens.is_empty());
+        assert!(authenticated_client(&state, &bearer("dummy_token")).is_some());
+        assert!(authenticated_client(&state, &bearer("test_dummy2")).is_none());
+
+        // The static token grants every scope, but isn't an admin token
+        assert!(
+            authorize_scope(&state, bearer("dummy_token"), Scope::WriteVens)
+                .await
+                .is_ok()
+        );
+        assert!(admin_authorizer(&state, bearer("dummy_token"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_admin_authorizer() {
+        let mut secrets_tree: BTreeMap<String, Secret<String>> = BTreeMap::new();
+        secrets_tree.insert(
+            "ADMIN_TOKEN".to_string(),
+            Secret::new("admin_token".to_string()),
+        );
+        let state = init_storage(SecretStore::new(secrets_tree)).await;
+        let status = |result: Result<(), (StatusCode, String)>| result.map_err(|err| err.0);
+
+        // Admin token and BL client tokens are accepted
+        assert_eq!(
+            status(admin_authorizer(&state, bearer("admin_token")).await),
+            Ok(())
+        );
+        let bl_client = OAuthClient {
+            role: ClientRole::BL,
+            ..client()
+        };
+        let bl_token = issue_token(&state, &bl_client, Some("read_all".to_string()));
+        assert_eq!(
+            status(admin_authorizer(&state, bearer(&bl_token.access_token)).await),
+            Ok(())
+        );
+
+        // VEN client tokens are forbidden, anything else is unauthorized
+        let ven_token = issue_token(&state, &client(), Some("read_all".to_string()));
+        assert_eq!(
+            status(admin_authorizer(&state, bearer(&ven_token.access_token)).await),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            status(admin_authorizer(&state, bearer("test_dummy2")).await),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            status(admin_authorizer(&state, HeaderMap::new()).await),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_has_credentials() {
+        let mut secrets_tree: BTreeMap<String, Secret<String>> = BTreeMap::new();
+        secrets_tree.insert(
+            "ADMIN_TOKEN".to_string(),
+            Secret::new("admin_token".to_string()),
+        );
+        let state = init_storage(SecretStore::new(secrets_tree)).await;
+        let ven_token = issue_token(&state, &client(), None);
+
+        assert!(has_credentials(&state, &bearer("admin_token")));
+        assert!(has_credentials(&state, &bearer(&ven_token.access_token)));
+        assert!(!has_credentials(&state, &bearer("test_dummy2")));
+        assert!(!has_credentials(&state, &HeaderMap::new()));
     }
 }
@@ -0,0 +1,311 @@
+use crate::utils::notifications::targets_overlap;
+use crate::utils::openadr_models::{ObjectTypes, OpenADREvent, Values, ValuesMap};
+use crate::utils::tokens::{IssuedToken, Scope};
+use crate::AppState;
+
+/// Check whether the client can see every object regardless of its owner and targets, ie. has the read_all scope
+pub fn sees_all(token: &IssuedToken) -> bool {
+    token.has_scope(Scope::ReadAll)
+}
+
+/// Record the client that created an object
+pub fn set_owner(state: &AppState, object_type: ObjectTypes, object_id: &str, token: &IssuedToken) {
+    state.owners.insert(
+        (object_type, object_id.to_string()),
+        token.client_id.clone(),
+    );
+}
+
+/// Forget the owner of a deleted object
+pub fn remove_owner(state: &AppState, object_type: ObjectTypes, object_id: &str) {
+    state.owners.remove(&(object_type, object_id.to_string()));
+}
+
+/// Check whether the client can modify an object
+///
+/// Only the client that created the object and clients with the read_all scope can modify it. Unlike with
+/// [`can_access`], objects without an owner, eg. created by the admin endpoints, aren't shared.
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `token`: The token of the calling client
+/// - `object_type`: The type of the object
+/// - `object_id`: The ID of the object
+///
+/// # Returns
+/// - `bool`: Whether the client can modify the object
+pub fn can_modify(
+    state: &AppState,
+    token: &IssuedToken,
+    object_type: ObjectTypes,
+    object_id: &str,
+) -> bool {
+    sees_all(token)
+        || state
+            .owners
+            .get(&(object_type, object_id.to_string()))
+            .is_some_and(|owner| *owner == token.client_id)
+}
+
+/// Check whether the client can access an object
+///
+/// Clients with the read_all scope can access every object, other clients only the objects they created. Objects
+/// without an owner, eg. created by the admin endpoints, are shared by all clients.
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `token`: The token of the calling client
+/// - `object_type`: The type of the object
+/// - `object_id`: The ID of the object
+///
+/// # Returns
+/// - `bool`: Whether the client can access the object
+pub fn can_access(
+    state: &AppState,
+    token: &IssuedToken,
+    object_type: ObjectTypes,
+    object_id: &str,
+) -> bool {
+    if sees_all(token) {
+        return true;
+    }
+    match state.owners.get(&(object_type, object_id.to_string())) {
+        Some(owner) => *owner == token.client_id,
+        None => true,
+    }
+}
+
+/// Collect the targets of the VENs registered by the client
+///
+/// Includes the names of the VENs as VEN_NAME targets and the names of the resources the client created for them as
+/// RESOURCE_NAME targets. VEN and resource names are unique, while the targets of a VEN are declared by the client
+/// itself and aren't used.
+pub fn client_targets(state: &AppState, client_id: &str) -> Vec<ValuesMap> {
+    let mut targets: Vec<ValuesMap> = Vec::new();
+    for ven in state.vens.iter() {
+        let owned = state
+            .owners
+            .get(&(ObjectTypes::VEN, ven.key().clone()))
+            .is_some_and(|owner| *owner == client_id);
+        if !owned {
+            continue;
+        }
+
+        targets.push(ValuesMap {
+            kind: "VEN_NAME".to_string(),
+            values: vec![Values::String(ven.ven_name.clone())],
+        });
+        targets.push(ValuesMap {
+            kind: "RESOURCE_NAME".to_string(),
+            values: state
+                .resources
+                .iter()
+                .filter(|resource| {
+                    resource.ven_id.as_ref() == Some(ven.key())
+                        && state
+                            .owners
+                            .get(&(ObjectTypes::RESOURCE, resource.key().clone()))
+                            .is_some_and(|owner| *owner == client_id)
+                })
+                .map(|resource| Values::String(resource.resource_name.clone()))
+                .collect(),
+        });
+    }
+    targets
+}
+
+/// Check whether the client can see an event
+///
+/// Clients with the read_all scope see every event. Other clients see the events they created, events without targets
+/// and events targeted to their VENs, see [`client_targets`].
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `token`: The token of the calling client
+/// - `event`: The event
+///
+/// # Returns
+/// - `bool`: Whether the client can see the event
+pub fn event_visible(state: &AppState, token: &IssuedToken, event: &OpenADREvent) -> bool {
+    sees_all(token) || client_sees_event(state, &token.client_id, event)
+}
+
+/// Check whether the owner of a subscription can see an event, so that the event can be delivered to it
+///
+/// Follows [`event_visible`]. The scopes of the owner aren't known outside of its requests, so owners that may request
+/// the read_all scope see every event. Subscriptions without an owner, eg. created by the admin endpoints, get every
+/// event.
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `subscription_id`: The ID of the subscription
+/// - `event`: The event
+///
+/// # Returns
+/// - `bool`: Whether the event can be delivered to the subscription
+pub fn subscriber_sees_event(
+    state: &AppState,
+    subscription_id: &str,
+    event: &OpenADREvent,
+) -> bool {
+    let owner = match state
+        .owners
+        .get(&(ObjectTypes::SUBSCRIPTION, subscription_id.to_string()))
+    {
+        Some(owner) => owner.clone(),
+        None => return true,
+    };
+    state
+        .clients
+        .get(&owner)
+        .is_some_and(|client| client.allows_scope(Scope::ReadAll.as_str()))
+        || client_sees_event(state, &owner, event)
+}
+
+/// Check whether a client without the read_all scope can see an event
+fn client_sees_event(state: &AppState, client_id: &str, event: &OpenADREvent) -> bool {
+    if let Some(event_id) = &event.id {
+        let owner = state.owners.get(&(ObjectTypes::EVENT, event_id.clone()));
+        if owner.is_some_and(|owner| *owner == client_id) {
+            return true;
+        }
+    }
+    match &event.targets {
+        Some(targets) if !targets.is_empty() => {
+            targets_overlap(targets, &client_targets(state, client_id))
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::init_storage::{dummy_event_to_storage, init_storage};
+    use crate::utils::oauth_clients::ClientRole;
+    use crate::utils::openadr_models::{Resource, Ven};
+    use shuttle_runtime::SecretStore;
+    use std::collections::BTreeMap;
+
+    fn token(client_id: &str, scope: &str) -> IssuedToken {
+        IssuedToken {
+            client_id: client_id.to_string(),
+            role: ClientRole::VEN,
+            expires_at: chrono::Utc::now() + chrono::Duration::seconds(60),
+            scope: Some(scope.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_can_access() {
+        let state = init_storage(SecretStore::new(BTreeMap::new())).await;
+        let ven1 = token("ven1", "read_targets");
+        set_owner(&state, ObjectTypes::REPORT, "report1", &ven1);
+
+        assert!(can_access(&state, &ven1, ObjectTypes::REPORT, "report1"));
+        assert!(!can_access(
+            &state,
+            &token("ven2", "read_targets"),
+            ObjectTypes::REPORT,
+            "report1"
+        ));
+        assert!(can_access(
+            &state,
+            &token("bl", "read_all"),
+            ObjectTypes::REPORT,
+            "report1"
+        ));
+
+        // Ownership is per object type, unowned objects are shared
+        assert!(can_access(
+            &state,
+            &token("ven2", "read_targets"),
+            ObjectTypes::SUBSCRIPTION,
+            "report1"
+        ));
+
+        remove_owner(&state, ObjectTypes::REPORT, "report1");
+        assert!(can_access(
+            &state,
+            &token("ven2", "read_targets"),
+            ObjectTypes::REPORT,
+            "report1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_can_modify() {
+        let state = init_storage(SecretStore::new(BTreeMap::new())).await;
+        let ven1 = token("ven1", "read_targets");
+        set_owner(&state, ObjectTypes::EVENT, "event1", &ven1);
+
+        assert!(can_modify(&state, &ven1, ObjectTypes::EVENT, "event1"));
+        assert!(!can_modify(
+            &state,
+            &token("ven2", "read_targets"),
+            ObjectTypes::EVENT,
+            "event1"
+        ));
+        // Unowned objects can only be modified with read_all
+        assert!(!can_modify(&state, &ven1, ObjectTypes::EVENT, "event2"));
+        assert!(can_modify(
+            &state,
+            &token("bl", "read_all"),
+            ObjectTypes::EVENT,
+            "event2"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_event_visible() {
+        let state = init_storage(SecretStore::new(BTreeMap::new())).await;
+        dummy_event_to_storage(&state).await;
+        let event = state.event_storage.read().await[0].clone();
+
+        // The dummy event targets the DUMMY resource
+        let ven1 = token("ven1", "read_targets");
+        assert!(!event_visible(&state, &ven1, &event));
+        assert!(event_visible(&state, &token("bl", "read_all"), &event));
+
+        state.vens.insert(
+            "ven-id".to_string(),
+            Ven {
+                id: Some("ven-id".to_string()),
+                created_date_time: None,
+                modification_date_time: None,
+                object_type: Some(ObjectTypes::VEN),
+                ven_name: "ven1".to_string(),
+                attributes: None,
+                // Targets declared by the client don't make events visible
+                targets: Some(vec![ValuesMap {
+                    kind: "RESOURCE_NAME".to_string(),
+                    values: vec![Values::String("DUMMY".to_string())],
+                }]),
+            },
+        );
+        state.resources.insert(
+            "resource-id".to_string(),
+            Resource {
+                id: Some("resource-id".to_string()),
+                created_date_time: None,
+                modification_date_time: None,
+                object_type: Some(ObjectTypes::RESOURCE),
+                resource_name: "DUMMY".to_string(),
+                ven_id: Some("ven-id".to_string()),
+                attributes: None,
+                targets: None,
+            },
+        );
+        set_owner(&state, ObjectTypes::VEN, "ven-id", &ven1);
+
+        // Only the resources created by the client count
+        assert!(!event_visible(&state, &ven1, &event));
+        set_owner(&state, ObjectTypes::RESOURCE, "resource-id", &ven1);
+        assert!(event_visible(&state, &ven1, &event));
+        assert!(!event_visible(
+            &state,
+            &token("ven2", "read_targets"),
+            &event
+        ));
+    }
+}
@@ -1,11 +1,26 @@
-use crate::utils::openadr_models::OpenADREvent;
+use crate::utils::init_storage::DUMMY_PROGRAM_ID;
+use crate::utils::iso8601::{IsoDuration, Timestamp};
+use crate::utils::openadr_models::{
+    EventPayloadType, Interval, IntervalPeriod, OpenADREvent, Values, ValuesMap,
+};
 use log::debug;
 use serde::{Deserialize, Serialize};
 
+/// Maximum number of intervals in a generated event
+pub const MAX_INTERVALS: usize = 1000;
+
 /// Create a test OpenADR event
 ///
 /// This function creates a test OpenADR event with the given parameters and returns it for use.
-/// Only creating events with limit type IMPORT_CAPACITY_LIMIT and payload type KW is supported for this tool.
+/// Any payload type of the specification is supported, IMPORT_CAPACITY_LIMIT in KW by default. The payload descriptor
+/// uses the units and currency of the payload type unless given in the parameters.
+///
+/// The event has a single interval by default. Multiple consecutive intervals are generated from a list of interval
+/// values, or from an interval count with the same value in every interval. The event interval period is the default
+/// period of each interval, so the length of the event parameters is the length of a single interval.
+///
+/// The event can start at an absolute time or at an offset from the current time. Negative offsets create events that
+/// are already ongoing or over.
 ///
 /// # Parameters
 /// - `body`: EventParameters - The parameters for the event
@@ -17,15 +32,18 @@ pub async fn create_test_oadr_event(body: EventParameters) -> OpenADREvent {
 
     // Grab time and create the start time for the event
     let now = chrono::Utc::now();
-    let start_time = now + chrono::Duration::minutes(body.minutes_in_future as i64);
-    let event_id = format!("test_event_{}", now.timestamp());
+    let start_time = body.start_time(Timestamp(now)).unwrap_or(Timestamp(now));
+    let duration = body.interval_duration();
+    let event_id = uuid::Uuid::new_v4().to_string();
+    let payload_type = body.payload_type.name();
+    let intervals = generate_intervals(&body, start_time, &payload_type);
 
     OpenADREvent {
         id: Some(event_id),
-        created_date_time: Some(now.to_rfc3339()),
-        modification_date_time: Some(now.to_rfc3339()),
+        created_date_time: Some(Timestamp(now)),
+        modification_date_time: Some(Timestamp(now)),
         object_type: Some(crate::utils::openadr_models::ObjectTypes::EVENT),
-        program_id: "1".to_string(),
+        program_id: DUMMY_PROGRAM_ID.to_string(),
         event_name: Some(body.event_name),
         priority: None,
         targets: Some(vec![
@@ -45,26 +63,87 @@ pub async fn create_test_oadr_event(body: EventParameters) -> OpenADREvent {
         report_descriptors: Some(vec![]),
         payload_descriptors: Some(vec![crate::utils::openadr_models::EventPayloadDescriptor {
             object_type: Some(crate::utils::openadr_models::PayloadDescriptorType::EVENT),
-            payload_type: "IMPORT_CAPACITY_LIMIT".to_string(),
-            units: Some("KW".to_string()),
-            currency: None,
+            payload_type: payload_type.clone(),
+            units: body
+                .units
+                .clone()
+                .or(body.payload_type.default_units().map(str::to_string)),
+            currency: body
+                .currency
+                .clone()
+                .or(body.payload_type.default_currency().map(str::to_string)),
         }]),
         interval_period: Some(crate::utils::openadr_models::IntervalPeriod {
-            start: start_time.to_rfc3339(),
-            duration: Some(format!("PT{}M", body.length)),
-            randomize_start: Some("PT0S".to_string()),
+            start: start_time,
+            duration,
+            randomize_start: Some(IsoDuration::from_seconds(0)),
         }),
-        intervals: vec![crate::utils::openadr_models::Interval {
-            id: 0,
-            interval_period: None,
-            payloads: vec![crate::utils::openadr_models::ValuesMap {
-                kind: "IMPORT_CAPACITY_LIMIT".to_string(),
-                values: vec![crate::utils::openadr_models::Values::Integer(body.limit_kw)],
-            }],
-        }],
+        intervals,
     }
 }
 
+/// Generate the numbered intervals of an event
+///
+/// Intervals without their own start or length follow each other from the event start. If any interval has its own
+/// start or length, every interval gets an explicit interval period so that the VEN doesn't have to compute them.
+///
+/// # Parameters
+/// - `body`: The parameters for the event
+/// - `start_time`: The start time of the event
+/// - `payload_type`: The payload type of the event
+///
+/// # Returns
+/// - `Vec<Interval>`: The intervals of the event
+fn generate_intervals(
+    body: &EventParameters,
+    start_time: Timestamp,
+    payload_type: &str,
+) -> Vec<Interval> {
+    let payload = |value: Option<Values>| {
+        vec![ValuesMap {
+            kind: payload_type.to_string(),
+            values: value.into_iter().collect(),
+        }]
+    };
+
+    let intervals = match &body.intervals {
+        Some(intervals) => intervals,
+        None => {
+            let count = body.interval_count.unwrap_or(1);
+            return (0..count)
+                .map(|id| Interval {
+                    id: id as i64,
+                    interval_period: None,
+                    payloads: payload(body.payload_value()),
+                })
+                .collect();
+        }
+    };
+
+    let explicit_periods = intervals
+        .iter()
+        .any(|interval| interval.start().is_some() || interval.duration().is_some());
+    let mut cursor = start_time;
+    intervals
+        .iter()
+        .enumerate()
+        .map(|(id, interval)| {
+            let interval_period = IntervalPeriod {
+                start: interval.start().copied().unwrap_or(cursor),
+                duration: interval.duration().or(body.interval_duration()),
+                randomize_start: None,
+            };
+            cursor = interval_period.end().unwrap_or(cursor);
+
+            Interval {
+                id: id as i64,
+                interval_period: explicit_periods.then_some(interval_period),
+                payloads: payload(Some(interval.value().clone())),
+            }
+        })
+        .collect()
+}
+
 /// Parameters for creating a test event
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -73,31 +152,198 @@ pub struct EventParameters {
     pub event_name: String,
     /// The oadr resource name to generate the event for
     pub oadr_resource_name: String,
-    /// The length of the event in minutes
-    pub length: i32,
-    /// The power limit during the event in kW
-    pub limit_kw: i64,
-    /// Event will trigger in this many minutes
-    pub minutes_in_future: i32,
+    /// The length of the event in minutes, or the length of each interval for multiple intervals
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<i32>,
+    /// The length in seconds, used instead of `length`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length_seconds: Option<i64>,
+    /// The payload type of the event, IMPORT_CAPACITY_LIMIT by default
+    #[serde(default)]
+    pub payload_type: EventPayloadType,
+    /// The payload value during the event, eg. a price, a setpoint or a SIMPLE level
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Values>,
+    /// The power limit during the event in kW, used as the value when `value` is not given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_kw: Option<i64>,
+    /// Units of the payload, overriding the units of the payload type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units: Option<String>,
+    /// Currency of the payload, overriding the currency of the payload type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// Event will trigger in this many minutes, negative for an event that has already started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minutes_in_future: Option<i32>,
+    /// Event will trigger in this many seconds, used instead of `minutesInFuture`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seconds_in_future: Option<i64>,
+    /// Absolute start time of the event, used instead of an offset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<Timestamp>,
+    /// Payload values of consecutive intervals, with optional start and length per interval
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intervals: Option<Vec<IntervalParameters>>,
+    /// Number of consecutive intervals with the payload value, eg. 24 with length 60 for a day of hourly intervals
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_count: Option<usize>,
+}
+
+/// Parameters of a generated interval, either the payload value as is or an object with the value
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum IntervalParameters {
+    Value(Values),
+    Interval(IntervalSpec),
+}
+
+/// Payload value of a generated interval with an optional start and length
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IntervalSpec {
+    /// The payload value during the interval
+    pub value: Values,
+    /// Start of the interval as an ISO 8601 date-time, the end of the previous interval by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<Timestamp>,
+    /// The length of the interval in minutes, the length of the event parameters by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<i32>,
+    /// The length of the interval in seconds, used instead of `length`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length_seconds: Option<i64>,
+}
+
+impl IntervalParameters {
+    pub fn value(&self) -> &Values {
+        match self {
+            IntervalParameters::Value(value) => value,
+            IntervalParameters::Interval(interval) => &interval.value,
+        }
+    }
+
+    pub fn start(&self) -> Option<&Timestamp> {
+        match self {
+            IntervalParameters::Value(_) => None,
+            IntervalParameters::Interval(interval) => interval.start.as_ref(),
+        }
+    }
+
+    /// The own length of the interval, None if not given or invalid
+    pub fn duration(&self) -> Option<IsoDuration> {
+        match self {
+            IntervalParameters::Value(_) => None,
+            IntervalParameters::Interval(interval) => {
+                duration_of(interval.length, interval.length_seconds)
+            }
+        }
+    }
+
+    /// Check that the interval has at most one positive length
+    pub fn is_valid(&self) -> bool {
+        match self {
+            IntervalParameters::Value(_) => true,
+            IntervalParameters::Interval(interval) => {
+                (interval.length.is_none() && interval.length_seconds.is_none())
+                    || self.duration().is_some()
+            }
+        }
+    }
+}
+
+impl EventParameters {
+    /// The payload value of the event, `value` or the legacy `limitKw`
+    pub fn payload_value(&self) -> Option<Values> {
+        self.value.clone().or(self.limit_kw.map(Values::Integer))
+    }
+
+    /// Start time of the event, the absolute `start` or the current time shifted by `minutesInFuture` or
+    /// `secondsInFuture`
+    ///
+    /// # Parameters
+    /// - `now`: The current time
+    ///
+    /// # Returns
+    /// - `Option<Timestamp>`: The start time, the current time if no start is given, or None if more than one start is
+    ///   given or the offset is out of range
+    pub fn start_time(&self, now: Timestamp) -> Option<Timestamp> {
+        let offset = match (self.start, self.minutes_in_future, self.seconds_in_future) {
+            (Some(start), None, None) => return Some(start),
+            (None, Some(minutes), None) => chrono::Duration::try_minutes(minutes as i64)?,
+            (None, None, Some(seconds)) => chrono::Duration::try_seconds(seconds)?,
+            (None, None, None) => chrono::Duration::zero(),
+            _ => return None,
+        };
+        now.0.checked_add_signed(offset).map(Timestamp)
+    }
+
+    /// Length of the event, or of each interval for multiple intervals, from `length` or `lengthSeconds`
+    pub fn interval_duration(&self) -> Option<IsoDuration> {
+        duration_of(self.length, self.length_seconds)
+    }
+
+    /// Check that the parameters describe a valid event
+    ///
+    /// Exactly one positive length is required, and at most one start. The start can be in the past. A payload value is
+    /// required unless the intervals are given, and the intervals can't be combined with an interval count. The legacy
+    /// `limitKw` has to be positive, other values are not restricted, eg. prices can be negative.
+    pub fn is_valid(&self) -> bool {
+        let intervals_valid = match (&self.intervals, self.interval_count) {
+            (Some(_), Some(_)) => false,
+            (Some(intervals), None) => {
+                (1..=MAX_INTERVALS).contains(&intervals.len())
+                    && intervals.iter().all(IntervalParameters::is_valid)
+            }
+            (None, count) => {
+                self.payload_value().is_some()
+                    && count.is_none_or(|count| (1..=MAX_INTERVALS).contains(&count))
+            }
+        };
+
+        self.interval_duration().is_some()
+            && self.start_time(Timestamp::now()).is_some()
+            && self.limit_kw.is_none_or(|limit_kw| limit_kw >= 1)
+            && intervals_valid
+    }
+}
+
+/// Duration from a length in minutes or seconds, None unless exactly one positive length is given
+fn duration_of(minutes: Option<i32>, seconds: Option<i64>) -> Option<IsoDuration> {
+    match (minutes, seconds) {
+        (Some(minutes), None) if minutes >= 1 => Some(IsoDuration::from_minutes(minutes as u64)),
+        (None, Some(seconds)) if seconds >= 1 => Some(IsoDuration::from_seconds(seconds as u64)),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::openadr_models::Values;
 
     #[tokio::test]
     async fn test_create_test_oadr_event() {
         let params = EventParameters {
             event_name: "test_event".to_string(),
             oadr_resource_name: "resource".to_string(),
-            length: 60,
-            limit_kw: 100,
-            minutes_in_future: 5,
+            length: Some(60),
+            length_seconds: None,
+            payload_type: EventPayloadType::default(),
+            value: None,
+            limit_kw: Some(100),
+            units: None,
+            currency: None,
+            minutes_in_future: Some(5),
+            seconds_in_future: None,
+            start: None,
+            intervals: None,
+            interval_count: None,
         };
 
-        let event = create_test_oadr_event(params).await;
+        let event = create_test_oadr_event(params.clone()).await;
 
+        // Events generated within the same second get distinct IDs
+        assert_ne!(event.id, create_test_oadr_event(params).await.id);
         assert_eq!(event.program_id, "1");
         assert_eq!(event.event_name, Some("test_event".to_string()));
         assert_eq!(
@@ -117,4 +363,167 @@ mod tests {
             Values::Integer(100)
         );
     }
+
+    #[tokio::test]
+    async fn test_create_test_oadr_event_payload_type() {
+        let params: EventParameters = serde_json::from_str(
+            r#"{"eventName":"price","oadrResourceName":"resource","length":60,"minutesInFuture":5,
+                "payloadType":"PRICE","value":-3}"#,
+        )
+        .unwrap();
+        assert!(params.is_valid());
+
+        let event = create_test_oadr_event(params.clone()).await;
+        let descriptor = &event.payload_descriptors.unwrap()[0];
+        assert_eq!(descriptor.payload_type, "PRICE");
+        assert_eq!(descriptor.units, Some("KWH".to_string()));
+        assert_eq!(descriptor.currency, Some("EUR".to_string()));
+        assert_eq!(event.intervals[0].payloads[0].kind, "PRICE");
+        assert_eq!(
+            event.intervals[0].payloads[0].values,
+            vec![Values::Integer(-3)]
+        );
+
+        // Units and currency can be overridden
+        let event = create_test_oadr_event(EventParameters {
+            currency: Some("USD".to_string()),
+            ..params.clone()
+        })
+        .await;
+        assert_eq!(
+            event.payload_descriptors.unwrap()[0].currency,
+            Some("USD".to_string())
+        );
+
+        // A payload value is required and the legacy limit has to be positive
+        assert!(!EventParameters {
+            value: None,
+            ..params.clone()
+        }
+        .is_valid());
+        assert!(!EventParameters {
+            limit_kw: Some(0),
+            ..params
+        }
+        .is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_create_test_oadr_event_intervals() {
+        let params: EventParameters = serde_json::from_str(
+            r#"{"eventName":"profile","oadrResourceName":"resource","length":60,"minutesInFuture":5,
+                "value":10,"intervalCount":24}"#,
+        )
+        .unwrap();
+        assert!(params.is_valid());
+
+        // Consecutive intervals follow the event interval period
+        let event = create_test_oadr_event(params.clone()).await;
+        assert_eq!(event.intervals.len(), 24);
+        assert_eq!(event.intervals[23].id, 23);
+        assert!(event.intervals[23].interval_period.is_none());
+        assert_eq!(
+            event.intervals[23].payloads[0].values,
+            vec![Values::Integer(10)]
+        );
+        assert_eq!(
+            event.interval_period.clone().unwrap().duration,
+            Some(IsoDuration::from_minutes(60))
+        );
+
+        // Interval values, the intervals after an interval with its own period follow it
+        let params = EventParameters {
+            value: None,
+            interval_count: None,
+            intervals: Some(
+                serde_json::from_str(
+                    r#"[1, {"value": 2, "start": "2030-01-01T00:00:00Z", "length": 30}, 3]"#,
+                )
+                .unwrap(),
+            ),
+            ..params
+        };
+        assert!(params.is_valid());
+        let event = create_test_oadr_event(params.clone()).await;
+        let ids: Vec<i64> = event.intervals.iter().map(|interval| interval.id).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+        assert_eq!(
+            event.intervals[1].payloads[0].values,
+            vec![Values::Integer(2)]
+        );
+        let last = event.intervals[2].interval_period.clone().unwrap();
+        assert_eq!(last.start.to_string(), "2030-01-01T00:30:00Z");
+        assert_eq!(last.end().unwrap().to_string(), "2030-01-01T01:30:00Z");
+
+        // Intervals can't be combined with a count, need a positive length and a valid start
+        assert!(!EventParameters {
+            interval_count: Some(2),
+            ..params.clone()
+        }
+        .is_valid());
+        assert!(!EventParameters {
+            intervals: Some(vec![IntervalParameters::Interval(IntervalSpec {
+                value: Values::Integer(1),
+                start: None,
+                length: Some(0),
+                length_seconds: None,
+            })]),
+            ..params
+        }
+        .is_valid());
+        assert!(
+            serde_json::from_str::<IntervalParameters>(r#"{"value": 1, "start": "tomorrow"}"#)
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_test_oadr_event_start() {
+        let params: EventParameters = serde_json::from_str(
+            r#"{"eventName":"ongoing","oadrResourceName":"resource","lengthSeconds":90,
+                "secondsInFuture":-30,"value":1}"#,
+        )
+        .unwrap();
+        assert!(params.is_valid());
+
+        // The event started 30 seconds ago and is still ongoing
+        let event = create_test_oadr_event(params.clone()).await;
+        let interval_period = event.interval_period.unwrap();
+        assert_eq!(interval_period.duration.unwrap().to_string(), "PT90S");
+        assert!(interval_period.start < Timestamp::now());
+        assert!(interval_period.end().unwrap() > Timestamp::now());
+
+        // Events can start at an absolute time, also in the past
+        let start: Timestamp = "2020-01-01T00:00:00Z".parse().unwrap();
+        let params = EventParameters {
+            seconds_in_future: None,
+            start: Some(start),
+            ..params
+        };
+        assert!(params.is_valid());
+        let event = create_test_oadr_event(params.clone()).await;
+        assert_eq!(event.interval_period.unwrap().start, start);
+
+        // Only one start and one length can be given
+        assert!(!EventParameters {
+            minutes_in_future: Some(-5),
+            ..params.clone()
+        }
+        .is_valid());
+        assert!(!EventParameters {
+            length: Some(1),
+            ..params.clone()
+        }
+        .is_valid());
+        assert!(!EventParameters {
+            length_seconds: None,
+            ..params.clone()
+        }
+        .is_valid());
+        assert!(!EventParameters {
+            length_seconds: Some(0),
+            ..params
+        }
+        .is_valid());
+    }
 }
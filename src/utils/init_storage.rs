@@ -1,15 +1,25 @@
+use crate::utils::iso8601::IsoDuration;
+use crate::utils::oauth_clients::{clients_from_secrets, OAuthClient};
 use crate::utils::openadr_models;
-use crate::utils::openadr_models::{OpenADREvent, Subscription, Values};
+use crate::utils::openadr_models::{
+    ObjectTypes, OpenADREvent, Program, Report, Resource, Subscription, Values, Ven,
+};
+use crate::utils::sessions::TestSession;
+use crate::utils::tokens::{token_lifetime_from_secrets, IssuedToken};
+use crate::utils::webhook_delivery::{DeliveryPolicy, DeliveryRecord};
 use crate::AppState;
 use dashmap::DashMap;
 use log::debug;
 use shuttle_runtime::SecretStore;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 /// Initialize the application state for the application
 ///
-/// Initialize event storage and subscriptions storage for the application in memory.
+/// Initialize event, subscription, program, report, VEN and resource storage for the application in memory, including
+/// the owners of the objects, along with the webhook delivery log and policy, the OAuth client registry, the token
+/// lifetime, the issued token registry and the test session registry.
 ///
 /// # Parameters
 /// - `secrets`: SecretStore - The secrets store for the application
@@ -19,31 +29,106 @@ use tokio::sync::RwLock;
 pub async fn init_storage(secrets: SecretStore) -> Arc<AppState> {
     debug!("Initializing storage");
 
+    // Webhook deliveries share a single client, timeouts are set per request according to the delivery policy
+    let delivery_policy = DeliveryPolicy::from_secrets(&secrets);
+    debug!("Webhook delivery policy: {:?}", delivery_policy);
+    let http_client = reqwest::Client::new();
+
+    let clients = clients_from_secrets(&secrets);
+    let token_lifetime = token_lifetime_from_secrets(&secrets);
+    let tokens: Arc<DashMap<String, IssuedToken>> = Arc::new(DashMap::new());
+
+    Arc::new(new_storage(
+        delivery_policy,
+        http_client,
+        clients,
+        token_lifetime,
+        tokens,
+        secrets,
+    ))
+}
+
+/// Initialize the application state of a test session
+///
+/// The session gets its own empty storage and delivery log, while the delivery policy, OAuth clients, token lifetime,
+/// issued tokens and secrets are shared with the application.
+///
+/// # Parameters
+/// - `shared_memory`: The shared memory state of the application
+///
+/// # Returns
+/// - `Arc<AppState>`: The shared memory state of the test session
+pub fn init_session_storage(shared_memory: &AppState) -> Arc<AppState> {
+    debug!("Initializing test session storage");
+
+    Arc::new(new_storage(
+        shared_memory.delivery_policy.clone(),
+        shared_memory.http_client.clone(),
+        shared_memory.clients.clone(),
+        shared_memory.token_lifetime,
+        shared_memory.tokens.clone(),
+        shared_memory.secrets.clone(),
+    ))
+}
+
+/// Build an application state with empty storage
+fn new_storage(
+    delivery_policy: DeliveryPolicy,
+    http_client: reqwest::Client,
+    clients: HashMap<String, OAuthClient>,
+    token_lifetime: chrono::Duration,
+    tokens: Arc<DashMap<String, IssuedToken>>,
+    secrets: SecretStore,
+) -> AppState {
     let event_storage: RwLock<Vec<OpenADREvent>> = RwLock::new(Vec::new());
 
     // Subscriptions use a map so that we can easily fetch/remove them by id
     let subscriptions: DashMap<String, Subscription> = DashMap::new();
-    let shared_memory = AppState {
+    let programs: DashMap<String, Program> = DashMap::new();
+    let reports: DashMap<String, Report> = DashMap::new();
+    let vens: DashMap<String, Ven> = DashMap::new();
+    let resources: DashMap<String, Resource> = DashMap::new();
+    let owners: DashMap<(ObjectTypes, String), String> = DashMap::new();
+    let delivery_log: RwLock<VecDeque<DeliveryRecord>> = RwLock::new(VecDeque::new());
+    let sessions: DashMap<String, TestSession> = DashMap::new();
+
+    AppState {
         event_storage,
         subscriptions,
+        programs,
+        reports,
+        vens,
+        resources,
+        owners,
+        delivery_log,
+        delivery_policy,
+        http_client,
+        clients,
+        token_lifetime,
+        tokens,
+        sessions,
+        session_lock: std::sync::Mutex::new(()),
         secrets,
-    };
-    Arc::new(shared_memory)
+    }
 }
 
-/// Add a dummy event to the event storage
+/// ID of the dummy program, used by the dummy event, the generated events and the initial subscription
+pub const DUMMY_PROGRAM_ID: &str = "1";
+
+/// Add a dummy event and its program to the storage
 ///
-/// Initial dummy event can be used for basic schema validation and event handling.
+/// Initial dummy event can be used for basic schema validation and event handling. The dummy program lets the VEN
+/// resolve the program of the dummy and generated events.
 ///
 /// # Parameters
 /// - `shared_memory`: The shared memory state of the application
 pub async fn dummy_event_to_storage(shared_memory: &Arc<AppState>) {
     let dummy_event: OpenADREvent = OpenADREvent {
         id: Some("dummyTest".to_string()),
-        created_date_time: Some("2024-03-06T10:55:26.543Z".to_string()),
-        modification_date_time: Some("2024-03-06T10:55:26.543Z".to_string()),
+        created_date_time: "2024-03-06T10:55:26.543Z".parse().ok(),
+        modification_date_time: "2024-03-06T10:55:26.543Z".parse().ok(),
         object_type: Some(openadr_models::ObjectTypes::EVENT),
-        program_id: "1".to_string(),
+        program_id: DUMMY_PROGRAM_ID.to_string(),
         event_name: Some("activationRequest".to_string()),
         priority: None,
         targets: Some(vec![
@@ -64,9 +149,11 @@ pub async fn dummy_event_to_storage(shared_memory: &Arc<AppState>) {
             currency: None,
         }]),
         interval_period: Some(openadr_models::IntervalPeriod {
-            start: "2024-09-04T10:30:30.000Z".to_string(),
-            duration: Some("PT2M".to_string()),
-            randomize_start: Some("PT0S".to_string()),
+            start: "2024-09-04T10:30:30.000Z"
+                .parse()
+                .expect("Invalid dummy event start"),
+            duration: Some(IsoDuration::from_minutes(2)),
+            randomize_start: Some(IsoDuration::from_seconds(0)),
         }),
         intervals: vec![openadr_models::Interval {
             id: 0,
@@ -78,6 +165,24 @@ pub async fn dummy_event_to_storage(shared_memory: &Arc<AppState>) {
         }],
     };
 
+    let dummy_program = Program {
+        id: Some(DUMMY_PROGRAM_ID.to_string()),
+        created_date_time: "2024-03-06T10:55:26.543Z".parse().ok(),
+        modification_date_time: "2024-03-06T10:55:26.543Z".parse().ok(),
+        object_type: Some(openadr_models::ObjectTypes::PROGRAM),
+        program_name: "testProgram".to_string(),
+        program_long_name: Some("Test VTN program".to_string()),
+        interval_period: None,
+        program_descriptions: None,
+        binding_events: None,
+        local_price: None,
+        payload_descriptors: None,
+        targets: None,
+    };
+    shared_memory
+        .programs
+        .insert(DUMMY_PROGRAM_ID.to_string(), dummy_program);
+
     let mut storage = shared_memory.event_storage.write().await;
     storage.push(dummy_event);
 }
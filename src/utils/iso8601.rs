@@ -0,0 +1,324 @@
+use chrono::{DateTime, Months, SecondsFormat, Utc};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// ISO 8601 date-time, eg. `2024-03-06T10:55:26.543Z`
+///
+/// Parsed according to the RFC 3339 profile of ISO 8601, so a time zone offset is required. The timestamp is kept in
+/// UTC and serialized with a `Z` suffix and as few fractional second digits as needed, ie. none, 3, 6 or 9, so eg.
+/// `10:00:00.000Z` is serialized as `10:00:00Z` and `10:00:00.5Z` as `10:00:00.500Z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(pub DateTime<Utc>);
+
+impl Timestamp {
+    /// The current time
+    pub fn now() -> Timestamp {
+        Timestamp(Utc::now())
+    }
+
+    /// Add a duration to the timestamp
+    ///
+    /// # Parameters
+    /// - `duration`: The duration to add, years and months are added as calendar months
+    ///
+    /// # Returns
+    /// - `Option<Timestamp>`: The timestamp after the duration, or None for an infinite duration or an overflow
+    pub fn checked_add(&self, duration: &IsoDuration) -> Option<Timestamp> {
+        if duration.is_infinite() {
+            return None;
+        }
+        let months =
+            (duration.years.unwrap_or(0) * 12).checked_add(duration.months.unwrap_or(0))?;
+        let date_time = self
+            .0
+            .checked_add_months(Months::new(u32::try_from(months).ok()?))?;
+        Some(Timestamp(
+            date_time.checked_add_signed(duration.fixed_part()?)?,
+        ))
+    }
+}
+
+impl From<DateTime<Utc>> for Timestamp {
+    fn from(date_time: DateTime<Utc>) -> Self {
+        Timestamp(date_time)
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        DateTime::parse_from_rfc3339(value)
+            .map(|date_time| Timestamp(date_time.with_timezone(&Utc)))
+            .map_err(|err| format!("Invalid ISO 8601 date-time {:?}: {}", value, err))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// ISO 8601 duration, eg. `PT1H30M` or `P1D`
+///
+/// The components are kept as given so that the duration serializes back to the parsed value. `P9999Y` is the infinite
+/// duration of the OpenADR 3.0 specification.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct IsoDuration {
+    pub years: Option<u64>,
+    pub months: Option<u64>,
+    pub weeks: Option<u64>,
+    pub days: Option<u64>,
+    pub hours: Option<u64>,
+    pub minutes: Option<u64>,
+    /// Seconds, the only component that can be fractional
+    pub seconds: Option<f64>,
+}
+
+impl IsoDuration {
+    /// Duration of whole minutes, eg. `PT15M`
+    pub fn from_minutes(minutes: u64) -> IsoDuration {
+        IsoDuration {
+            minutes: Some(minutes),
+            ..IsoDuration::default()
+        }
+    }
+
+    /// Duration of whole seconds, eg. `PT0S`
+    pub fn from_seconds(seconds: u64) -> IsoDuration {
+        IsoDuration {
+            seconds: Some(seconds as f64),
+            ..IsoDuration::default()
+        }
+    }
+
+    /// Check whether the duration is infinite, ie. 9999 years or more
+    pub fn is_infinite(&self) -> bool {
+        self.years.is_some_and(|years| years >= 9999)
+    }
+
+    /// Length of the weeks, days, hours, minutes and seconds of the duration
+    fn fixed_part(&self) -> Option<chrono::Duration> {
+        let whole_seconds = [
+            (self.weeks, 7 * 24 * 3600),
+            (self.days, 24 * 3600),
+            (self.hours, 3600),
+            (self.minutes, 60),
+        ]
+        .iter()
+        .try_fold(0u64, |total, (component, seconds)| {
+            total.checked_add(component.unwrap_or(0).checked_mul(*seconds)?)
+        })?;
+        let seconds = self.seconds.unwrap_or(0.0);
+        chrono::Duration::try_seconds(i64::try_from(whole_seconds).ok()?)?.checked_add(
+            &chrono::Duration::try_milliseconds((seconds * 1000.0).round() as i64)?,
+        )
+    }
+}
+
+impl FromStr for IsoDuration {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid ISO 8601 duration {:?}", value);
+        let rest = value.strip_prefix('P').ok_or_else(invalid)?;
+        let (date, time) = match rest.split_once('T') {
+            Some((_, "")) => return Err(invalid()),
+            Some((date, time)) => (date, time),
+            None => (rest, ""),
+        };
+        if date.is_empty() && time.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut duration = IsoDuration::default();
+        for (part, designators) in [(date, "YMWD"), (time, "HMS")] {
+            let mut number = String::new();
+            let mut next_designator = 0;
+            for c in part.chars() {
+                if c.is_ascii_digit() || (designators == "HMS" && (c == '.' || c == ',')) {
+                    number.push(if c == ',' { '.' } else { c });
+                    continue;
+                }
+                // Designators have to be in order and follow a number
+                let position = designators[next_designator..]
+                    .find(c)
+                    .map(|position| position + next_designator)
+                    .filter(|_| !number.is_empty())
+                    .ok_or_else(invalid)?;
+                next_designator = position + 1;
+
+                if c == 'S' {
+                    duration.seconds = Some(number.parse().map_err(|_| invalid())?);
+                } else {
+                    let component: u64 = number.parse().map_err(|_| invalid())?;
+                    match (designators, c) {
+                        ("YMWD", 'Y') => duration.years = Some(component),
+                        ("YMWD", 'M') => duration.months = Some(component),
+                        ("YMWD", 'W') => duration.weeks = Some(component),
+                        ("YMWD", 'D') => duration.days = Some(component),
+                        ("HMS", 'H') => duration.hours = Some(component),
+                        _ => duration.minutes = Some(component),
+                    }
+                }
+                number.clear();
+            }
+            if !number.is_empty() {
+                return Err(invalid());
+            }
+        }
+        Ok(duration)
+    }
+}
+
+impl fmt::Display for IsoDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let date = [
+            (self.years, 'Y'),
+            (self.months, 'M'),
+            (self.weeks, 'W'),
+            (self.days, 'D'),
+        ];
+        let time = [(self.hours, 'H'), (self.minutes, 'M')];
+
+        f.write_str("P")?;
+        for (component, designator) in date {
+            if let Some(component) = component {
+                write!(f, "{}{}", component, designator)?;
+            }
+        }
+        if time.iter().any(|(component, _)| component.is_some()) || self.seconds.is_some() {
+            f.write_str("T")?;
+            for (component, designator) in time {
+                if let Some(component) = component {
+                    write!(f, "{}{}", component, designator)?;
+                }
+            }
+            if let Some(seconds) = self.seconds {
+                write!(f, "{}S", seconds)?;
+            }
+        } else if date.iter().all(|(component, _)| component.is_none()) {
+            f.write_str("T0S")?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for IsoDuration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IsoDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        let timestamp: Timestamp = "2024-03-06T10:55:26.543Z".parse().unwrap();
+        assert_eq!(timestamp.to_string(), "2024-03-06T10:55:26.543Z");
+
+        // Offsets are normalized to UTC
+        let timestamp: Timestamp = "2024-03-06T12:00:00+02:00".parse().unwrap();
+        assert_eq!(timestamp.to_string(), "2024-03-06T10:00:00Z");
+
+        // The fractional seconds are written with 0, 3, 6 or 9 digits
+        for (value, serialized) in [
+            ("2024-03-06T10:00:00.000Z", "2024-03-06T10:00:00Z"),
+            ("2024-03-06T10:00:00.5Z", "2024-03-06T10:00:00.500Z"),
+            ("2024-03-06T10:00:00.1234Z", "2024-03-06T10:00:00.123400Z"),
+        ] {
+            assert_eq!(value.parse::<Timestamp>().unwrap().to_string(), serialized);
+        }
+
+        assert!("2024-03-06".parse::<Timestamp>().is_err());
+        assert!("2024-03-06T10:00:00".parse::<Timestamp>().is_err());
+        assert!(serde_json::from_str::<Timestamp>(r#""yesterday""#).is_err());
+    }
+
+    #[test]
+    fn test_duration_round_trip() {
+        for value in [
+            "PT1H",
+            "PT15M",
+            "P1D",
+            "P0D",
+            "PT0S",
+            "P1Y2M3DT4H5M6S",
+            "P2W",
+            "PT0.5S",
+            "P9999Y",
+        ] {
+            let duration: IsoDuration = value.parse().unwrap();
+            assert_eq!(duration.to_string(), value);
+            assert_eq!(
+                serde_json::to_string(&duration).unwrap(),
+                format!("\"{}\"", value)
+            );
+        }
+        assert_eq!(IsoDuration::from_minutes(30).to_string(), "PT30M");
+        assert_eq!(IsoDuration::from_seconds(0).to_string(), "PT0S");
+        assert_eq!(IsoDuration::default().to_string(), "PT0S");
+    }
+
+    #[test]
+    fn test_invalid_duration() {
+        for value in [
+            "", "P", "PT", "1H", "PT1D", "P1H", "PT1M1H", "PT1.5M", "P1DT", "PTH",
+        ] {
+            assert!(value.parse::<IsoDuration>().is_err(), "{}", value);
+        }
+        assert!(serde_json::from_str::<IsoDuration>(r#""15 minutes""#).is_err());
+
+        // Parseable, but too long to compute an end time
+        let start = Timestamp::now();
+        assert_eq!(
+            start.checked_add(&"P99999999999999999W".parse().unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_duration_end_time() {
+        let start: Timestamp = "2024-01-31T12:00:00Z".parse().unwrap();
+        let end = |duration: &str| {
+            start
+                .checked_add(&duration.parse().unwrap())
+                .map(|end| end.to_string())
+        };
+
+        assert_eq!(end("PT1H30M"), Some("2024-01-31T13:30:00Z".to_string()));
+        assert_eq!(end("P1W"), Some("2024-02-07T12:00:00Z".to_string()));
+        // Months are calendar months
+        assert_eq!(end("P1M"), Some("2024-02-29T12:00:00Z".to_string()));
+        assert_eq!(end("P9999Y"), None);
+        // Durations beyond the range of chrono overflow instead of panicking
+        assert_eq!(end("PT2562047788015H1000S"), None);
+    }
+}
@@ -1,4 +1,16 @@
 pub(crate) mod authorizer;
+pub(crate) mod client_access;
 pub(crate) mod create_test_oadr_event;
 pub(crate) mod init_storage;
+pub(crate) mod iso8601;
+pub(crate) mod oauth_clients;
+pub(crate) mod notifications;
 pub(crate) mod openadr_models;
+pub(crate) mod query_filters;
+pub(crate) mod resource_validation;
+pub(crate) mod sessions;
+pub(crate) mod token_request;
+pub(crate) mod tokens;
+pub(crate) mod webhook_delivery;
+#[cfg(test)]
+pub(crate) mod test_helpers;
@@ -0,0 +1,316 @@
+use crate::utils::client_access::subscriber_sees_event;
+use crate::utils::openadr_models::{
+    Notification, NotificationObject, ObjectOperation, ObjectTypes, OpenADREvent, Operation,
+    Subscription, Values, ValuesMap,
+};
+use crate::utils::webhook_delivery::deliver;
+use crate::AppState;
+use log::info;
+use shuttle_runtime::SecretStore;
+use std::sync::Arc;
+
+/// Build the request body sent to a subscription callback URL
+///
+/// By default the body is the OpenADR 3.0 notification envelope. Older VEN builds expect the raw object instead,
+/// which can be enabled with the optional `LEGACY_RAW_NOTIFICATIONS = "true"` secret.
+///
+/// # Parameters
+/// - `secrets`: The secrets store of the application
+/// - `notification`: The notification to send
+///
+/// # Returns
+/// - `serde_json::Value`: The JSON body for the callback request
+pub fn notification_body(secrets: &SecretStore, notification: &Notification) -> serde_json::Value {
+    let legacy_raw = secrets
+        .get("LEGACY_RAW_NOTIFICATIONS")
+        .is_some_and(|value| value == "true");
+
+    let body = if legacy_raw {
+        // The notification object is untagged, so it serializes as the plain object
+        serde_json::to_value(&notification.object)
+    } else {
+        serde_json::to_value(notification)
+    };
+
+    body.expect("Notification serialization failed")
+}
+
+/// Send a notification to every subscription interested in it
+///
+/// A subscription is notified through each of its object operations that include the object type and operation
+/// of the notification, provided that the program ID and targets of the subscription match the object. Events are
+/// only sent to subscriptions whose owner can see the event, see [`subscriber_sees_event`].
+/// The deliveries are made in the background so that the calling handler doesn't wait for the VENs to respond,
+/// their outcome can be found in the delivery log.
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `notification`: The notification to send
+/// - `program_id`: The program ID of the object, if the object belongs to a program
+pub fn notify_subscribers(
+    state: &Arc<AppState>,
+    notification: &Notification,
+    program_id: Option<&str>,
+) {
+    let body = notification_body(&state.secrets, notification);
+
+    for subscription in state.subscriptions.iter() {
+        if !subscription_matches(subscription.value(), notification, program_id) {
+            continue;
+        }
+        if let NotificationObject::Event(event) = &notification.object {
+            if !subscriber_sees_event(state, subscription.key(), event) {
+                continue;
+            }
+        }
+
+        for object_operation in &subscription.object_operations {
+            if !object_operation_matches(object_operation, notification) {
+                continue;
+            }
+
+            info!(
+                "Notifying subscription {:?} of {:?} {:?}",
+                subscription.id, notification.operation, notification.object_type
+            );
+            let state = state.clone();
+            let subscription_id = subscription.id.clone();
+            let object_operation = object_operation.clone();
+            let body = body.clone();
+            tokio::spawn(async move {
+                deliver(&state, subscription_id, &object_operation, &body).await;
+            });
+        }
+    }
+}
+
+/// Notify every subscription interested in an operation on an event
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `operation`: The operation performed on the event
+/// - `event`: The created, updated or deleted event
+pub fn notify_event_subscribers(state: &Arc<AppState>, operation: Operation, event: &OpenADREvent) {
+    let notification = Notification {
+        object_type: ObjectTypes::EVENT,
+        operation,
+        targets: event.targets.clone(),
+        object: NotificationObject::Event(event.clone()),
+    };
+    notify_subscribers(state, &notification, Some(&event.program_id));
+}
+
+/// Check whether the program and targets of a subscription match the object of a notification
+///
+/// Subscriptions without targets match every object, and objects without targets match every subscription.
+fn subscription_matches(
+    subscription: &Subscription,
+    notification: &Notification,
+    program_id: Option<&str>,
+) -> bool {
+    if program_id.is_some_and(|program_id| program_id != subscription.program_id) {
+        return false;
+    }
+
+    match (&subscription.targets, &notification.targets) {
+        (Some(subscription_targets), Some(object_targets))
+            if !subscription_targets.is_empty() && !object_targets.is_empty() =>
+        {
+            targets_overlap(subscription_targets, object_targets)
+        }
+        _ => true,
+    }
+}
+
+/// Check whether an object operation subscribes to the object type and operation of a notification
+fn object_operation_matches(
+    object_operation: &ObjectOperation,
+    notification: &Notification,
+) -> bool {
+    object_operation
+        .object_type
+        .contains(&notification.object_type)
+        && object_operation
+            .operations
+            .operations
+            .contains(&notification.operation)
+}
+
+/// Check whether any target of the first list shares a type and a value with a target of the second list
+pub fn targets_overlap(first: &[ValuesMap], second: &[ValuesMap]) -> bool {
+    first.iter().any(|first_target| {
+        second
+            .iter()
+            .filter(|second_target| second_target.kind == first_target.kind)
+            .any(|second_target| {
+                first_target
+                    .values
+                    .iter()
+                    .any(|value: &Values| second_target.values.contains(value))
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::init_storage::dummy_event_to_storage;
+    use crate::utils::openadr_models::{Operations, Program};
+    use crate::utils::test_helpers::{callback_server, store_subscription, test_state};
+    use axum::http::StatusCode;
+    use shuttle_common::Secret;
+    use std::collections::BTreeMap;
+
+    fn resource_targets(name: &str) -> Option<Vec<ValuesMap>> {
+        Some(vec![ValuesMap {
+            kind: "RESOURCE_NAME".to_string(),
+            values: vec![Values::String(name.to_string())],
+        }])
+    }
+
+    #[test]
+    fn test_subscription_matching() {
+        let mut subscription = Subscription {
+            id: Some("test".to_string()),
+            created_date_time: None,
+            modification_date_time: None,
+            object_type: Some(ObjectTypes::SUBSCRIPTION),
+            client_name: "test_client".to_string(),
+            program_id: "1".to_string(),
+            object_operations: vec![ObjectOperation {
+                object_type: vec![ObjectTypes::EVENT],
+                operations: Operations {
+                    operations: vec![Operation::POST, Operation::DELETE],
+                },
+                callback_url: "https://example.com".to_string(),
+                bearer_token: "".to_string(),
+            }],
+            targets: resource_targets("charger1"),
+        };
+        let notification = Notification {
+            object_type: ObjectTypes::EVENT,
+            operation: Operation::POST,
+            targets: resource_targets("charger1"),
+            object: NotificationObject::Subscription(subscription.clone()),
+        };
+
+        assert!(subscription_matches(
+            &subscription,
+            &notification,
+            Some("1")
+        ));
+        assert!(!subscription_matches(
+            &subscription,
+            &notification,
+            Some("2")
+        ));
+        assert!(object_operation_matches(
+            &subscription.object_operations[0],
+            &notification
+        ));
+
+        // Operation not subscribed to
+        let put_notification = Notification {
+            operation: Operation::PUT,
+            ..notification.clone()
+        };
+        assert!(!object_operation_matches(
+            &subscription.object_operations[0],
+            &put_notification
+        ));
+
+        // Targets have to overlap, unless either side has no targets
+        subscription.targets = resource_targets("charger2");
+        assert!(!subscription_matches(
+            &subscription,
+            &notification,
+            Some("1")
+        ));
+        subscription.targets = None;
+        assert!(subscription_matches(
+            &subscription,
+            &notification,
+            Some("1")
+        ));
+    }
+
+    #[test]
+    fn test_notification_body() {
+        let notification = Notification {
+            object_type: ObjectTypes::PROGRAM,
+            operation: Operation::PUT,
+            targets: None,
+            object: NotificationObject::Program(Program {
+                id: Some("program".to_string()),
+                created_date_time: None,
+                modification_date_time: None,
+                object_type: Some(ObjectTypes::PROGRAM),
+                program_name: "test_program".to_string(),
+                program_long_name: None,
+                interval_period: None,
+                program_descriptions: None,
+                binding_events: None,
+                local_price: None,
+                payload_descriptors: None,
+                targets: None,
+            }),
+        };
+
+        // Notification envelope by default
+        let secrets = SecretStore::new(BTreeMap::new());
+        let body = notification_body(&secrets, &notification);
+        assert_eq!(body["objectType"], "PROGRAM");
+        assert_eq!(body["operation"], "PUT");
+        assert_eq!(body["object"]["programName"], "test_program");
+
+        // Raw object in legacy mode
+        let mut secrets_tree: BTreeMap<String, Secret<String>> = BTreeMap::new();
+        secrets_tree.insert(
+            "LEGACY_RAW_NOTIFICATIONS".to_string(),
+            Secret::new("true".to_string()),
+        );
+        let secrets = SecretStore::new(secrets_tree);
+        let body = notification_body(&secrets, &notification);
+        assert_eq!(body["programName"], "test_program");
+        assert!(body.get("operation").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_event_delivery_scoped_by_owner() {
+        let state = test_state(&[]).await;
+        dummy_event_to_storage(&state).await;
+        let (callback_url, received) = callback_server(StatusCode::OK).await;
+        // A subscription without targets owned by client A
+        store_subscription(&state, "sub-a", ObjectTypes::EVENT, &[&callback_url]);
+        state.owners.insert(
+            (ObjectTypes::SUBSCRIPTION, "sub-a".to_string()),
+            "ven-a".to_string(),
+        );
+
+        // An event created by client B and targeted at its resource isn't delivered to client A
+        let mut event = state.event_storage.read().await[0].clone();
+        event.id = Some("event-b".to_string());
+        event.targets = resource_targets("resource-b");
+        state.owners.insert(
+            (ObjectTypes::EVENT, "event-b".to_string()),
+            "ven-b".to_string(),
+        );
+        assert!(!subscriber_sees_event(&state, "sub-a", &event));
+        notify_event_subscribers(&state, Operation::POST, &event);
+
+        // Events without targets are
+        event.id = Some("event-all".to_string());
+        event.targets = None;
+        notify_event_subscribers(&state, Operation::POST, &event);
+
+        for _ in 0..50 {
+            if !received.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0]["object"]["id"], "event-all");
+    }
+}
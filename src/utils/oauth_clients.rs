@@ -0,0 +1,147 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use shuttle_runtime::SecretStore;
+use std::collections::HashMap;
+
+/// Role of an OAuth client, as defined by OpenADR 3.0
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum ClientRole {
+    /// Virtual end node, the default
+    #[default]
+    VEN,
+    /// Business logic, eg. a test orchestrator administering the VTN
+    BL,
+}
+
+/// OAuth client allowed to fetch tokens from the auth endpoint
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthClient {
+    /// Client ID, the username of the client credentials
+    pub client_id: String,
+    /// Client secret, the password of the client credentials
+    pub client_secret: String,
+    /// Scopes the client may request, any scope is allowed if not set
+    #[serde(default)]
+    pub scopes: Option<Vec<String>>,
+    /// Role of the client
+    #[serde(default)]
+    pub role: ClientRole,
+}
+
+impl OAuthClient {
+    /// Check whether the client may request all of the space separated scopes
+    pub fn allows_scope(&self, scope: &str) -> bool {
+        match &self.scopes {
+            Some(allowed) => scope
+                .split_whitespace()
+                .all(|scope| allowed.iter().any(|allowed| allowed == scope)),
+            None => true,
+        }
+    }
+}
+
+/// Build the client registry from the secrets
+///
+/// Clients are configured with the optional `OAUTH_CLIENTS` secret as a JSON array of clients, eg.
+/// `[{"clientId": "ven1", "clientSecret": "secret", "scopes": ["read_all"], "role": "VEN"}]`. The client of the
+/// `BASIC_AUTH_HEADER` secret is also registered with the VEN role and any scope, unless a client with the same ID is
+/// configured.
+///
+/// # Parameters
+/// - `secrets`: The secrets store of the application
+///
+/// # Returns
+/// - `HashMap<String, OAuthClient>`: The registered clients by client ID
+pub fn clients_from_secrets(secrets: &SecretStore) -> HashMap<String, OAuthClient> {
+    let mut clients: HashMap<String, OAuthClient> = HashMap::new();
+
+    if let Some(basic_auth_header) = secrets.get("BASIC_AUTH_HEADER") {
+        match decode_basic_auth_header(&basic_auth_header) {
+            Some((client_id, client_secret)) => {
+                clients.insert(
+                    client_id.clone(),
+                    OAuthClient {
+                        client_id,
+                        client_secret,
+                        scopes: None,
+                        role: ClientRole::VEN,
+                    },
+                );
+            }
+            None => warn!("BASIC_AUTH_HEADER is not a valid Basic auth header"),
+        }
+    }
+
+    if let Some(configured) = secrets.get("OAUTH_CLIENTS") {
+        let configured: Vec<OAuthClient> = serde_json::from_str(&configured)
+            .expect("OAUTH_CLIENTS must be a JSON array of clients");
+        for client in configured {
+            clients.insert(client.client_id.clone(), client);
+        }
+    }
+
+    if clients.is_empty() {
+        warn!("No OAuth clients configured, tokens can't be fetched from the auth endpoint");
+    }
+    debug!("Registered OAuth clients: {:?}", clients.keys());
+    clients
+}
+
+/// Decode the client ID and secret from a Basic auth header value
+pub fn decode_basic_auth_header(header: &str) -> Option<(String, String)> {
+    let encoded = header.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+    Some((client_id.to_string(), client_secret.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shuttle_common::Secret;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_clients_from_secrets() {
+        let mut secrets_tree: BTreeMap<String, Secret<String>> = BTreeMap::new();
+        secrets_tree.insert(
+            "BASIC_AUTH_HEADER".to_string(),
+            Secret::new("Basic bGVnYWN5OnNlY3JldA==".to_string()),
+        );
+        secrets_tree.insert(
+            "OAUTH_CLIENTS".to_string(),
+            Secret::new(
+                r#"[{"clientId": "ven1", "clientSecret": "ven1secret", "scopes": ["read_all", "write_reports"]},
+                    {"clientId": "orchestrator", "clientSecret": "secret", "role": "BL"}]"#
+                    .to_string(),
+            ),
+        );
+        let clients = clients_from_secrets(&SecretStore::new(secrets_tree));
+
+        let legacy = &clients["legacy"];
+        assert_eq!(legacy.client_secret, "secret");
+        assert_eq!(legacy.role, ClientRole::VEN);
+        assert!(legacy.allows_scope("write_events"));
+
+        let ven = &clients["ven1"];
+        assert_eq!(ven.role, ClientRole::VEN);
+        assert!(ven.allows_scope("read_all write_reports"));
+        assert!(!ven.allows_scope("read_all write_events"));
+
+        assert_eq!(clients["orchestrator"].role, ClientRole::BL);
+    }
+
+    #[test]
+    fn test_decode_basic_auth_header() {
+        assert_eq!(
+            decode_basic_auth_header("Basic dmVuOnNlY3JldA=="),
+            Some(("ven".to_string(), "secret".to_string()))
+        );
+        assert_eq!(decode_basic_auth_header("Bearer dmVuOnNlY3JldA=="), None);
+        assert_eq!(decode_basic_auth_header("Basic not base64"), None);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::utils::iso8601::{IsoDuration, Timestamp};
 use serde::{Deserialize, Serialize};
 
 /// OpenADR event object
@@ -10,10 +11,10 @@ pub struct OpenADREvent {
     pub id: Option<String>,
     /// VTN Provisioned on creation
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub created_date_time: Option<String>,
+    pub created_date_time: Option<Timestamp>,
     /// VTN Provisioned on modification
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub modification_date_time: Option<String>,
+    pub modification_date_time: Option<Timestamp>,
     /// Used as discriminator, eg. notification object
     #[serde(skip_serializing_if = "Option::is_none")]
     pub object_type: Option<ObjectTypes>,
@@ -68,6 +69,99 @@ pub enum PayloadDescriptorType {
     REPORT,
 }
 
+/// Event payload types of the OpenADR 3.0 specification
+///
+/// The payload type of an [`EventPayloadDescriptor`] is kept as a string, since the specification allows private payload
+/// types. This enumeration is used by the event generator.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[allow(clippy::upper_case_acronyms, non_camel_case_types)]
+pub enum EventPayloadType {
+    SIMPLE,
+    PRICE,
+    CHARGE_STATE_SETPOINT,
+    DISPATCH_SETPOINT,
+    DISPATCH_SETPOINT_RELATIVE,
+    CONTROL_SETPOINT,
+    EXPORT_PRICE,
+    GHG,
+    CURVE,
+    OLS,
+    IMPORT_CAPACITY_SUBSCRIPTION,
+    IMPORT_CAPACITY_RESERVATION,
+    IMPORT_CAPACITY_RESERVATION_FEE,
+    IMPORT_CAPACITY_AVAILABLE,
+    IMPORT_CAPACITY_AVAILABLE_PRICE,
+    EXPORT_CAPACITY_SUBSCRIPTION,
+    EXPORT_CAPACITY_RESERVATION,
+    EXPORT_CAPACITY_RESERVATION_FEE,
+    EXPORT_CAPACITY_AVAILABLE,
+    EXPORT_CAPACITY_AVAILABLE_PRICE,
+    #[default]
+    IMPORT_CAPACITY_LIMIT,
+    EXPORT_CAPACITY_LIMIT,
+    ALERT_GRID_EMERGENCY,
+    ALERT_BLACK_START,
+    ALERT_POSSIBLE_OUTAGE,
+    ALERT_FLEX_ALERT,
+    ALERT_FIRE,
+    ALERT_FREEZING,
+    ALERT_WIND,
+    ALERT_TSUNAMI,
+    ALERT_AIR_QUALITY,
+    ALERT_OTHER,
+    CTA2045_REBOOT,
+    CTA2045_SET_OVERRIDE_STATUS,
+}
+impl EventPayloadType {
+    /// Name of the payload type as serialized, eg. `IMPORT_CAPACITY_LIMIT`
+    ///
+    /// Derived from the serde names, so that the payload type of the generated payloads always matches the payload type
+    /// given to the event generator.
+    pub fn name(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|name| name.as_str().map(str::to_string))
+            .expect("Payload types serialize as strings")
+    }
+
+    /// Units of the payload values. Prices are per unit, eg. EUR/KWH for PRICE. Types with units fixed by the
+    /// specification, eg. GHG in g/kWh, have no units.
+    pub fn default_units(&self) -> Option<&'static str> {
+        match self {
+            EventPayloadType::PRICE | EventPayloadType::EXPORT_PRICE => Some("KWH"),
+            EventPayloadType::CHARGE_STATE_SETPOINT => Some("PERCENT"),
+            EventPayloadType::DISPATCH_SETPOINT
+            | EventPayloadType::DISPATCH_SETPOINT_RELATIVE
+            | EventPayloadType::IMPORT_CAPACITY_SUBSCRIPTION
+            | EventPayloadType::IMPORT_CAPACITY_RESERVATION
+            | EventPayloadType::IMPORT_CAPACITY_RESERVATION_FEE
+            | EventPayloadType::IMPORT_CAPACITY_AVAILABLE
+            | EventPayloadType::IMPORT_CAPACITY_AVAILABLE_PRICE
+            | EventPayloadType::EXPORT_CAPACITY_SUBSCRIPTION
+            | EventPayloadType::EXPORT_CAPACITY_RESERVATION
+            | EventPayloadType::EXPORT_CAPACITY_RESERVATION_FEE
+            | EventPayloadType::EXPORT_CAPACITY_AVAILABLE
+            | EventPayloadType::EXPORT_CAPACITY_AVAILABLE_PRICE
+            | EventPayloadType::IMPORT_CAPACITY_LIMIT
+            | EventPayloadType::EXPORT_CAPACITY_LIMIT => Some("KW"),
+            _ => None,
+        }
+    }
+
+    /// Currency of the payload values, only set for prices and fees
+    pub fn default_currency(&self) -> Option<&'static str> {
+        match self {
+            EventPayloadType::PRICE
+            | EventPayloadType::EXPORT_PRICE
+            | EventPayloadType::IMPORT_CAPACITY_RESERVATION_FEE
+            | EventPayloadType::IMPORT_CAPACITY_AVAILABLE_PRICE
+            | EventPayloadType::EXPORT_CAPACITY_RESERVATION_FEE
+            | EventPayloadType::EXPORT_CAPACITY_AVAILABLE_PRICE => Some("EUR"),
+            _ => None,
+        }
+    }
+}
+
 /// An object that may be used to request a report from a VEN
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ReportDescriptor {
@@ -122,14 +216,21 @@ pub struct Interval {
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct IntervalPeriod {
     /// Start time of the interval in iso8601 format
-    pub start: String,
-    /// Duration of the interval in iso8601 format
+    pub start: Timestamp,
+    /// Duration of the interval in iso8601 format, P9999Y for infinite
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub duration: Option<String>,
+    pub duration: Option<IsoDuration>,
     /// Randomize start time range, can be added as a duration to the start time
     #[serde(rename = "randomizeStart")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub randomize_start: Option<String>,
+    pub randomize_start: Option<IsoDuration>,
+}
+
+impl IntervalPeriod {
+    /// End time of the period, None if the period has no duration or an infinite duration
+    pub fn end(&self) -> Option<Timestamp> {
+        self.start.checked_add(self.duration.as_ref()?)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -139,15 +240,29 @@ pub struct ValuesMap {
     pub values: Vec<Values>,
 }
 
+/// Value of a ValuesMap, one of the value types allowed by the specification
+///
+/// The variants are tried in order when deserializing, so Integer has to stay before Float for integers to round-trip
+/// as integers. Floats are serialized in their shortest exact representation.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum Values {
     String(String),
     Integer(i64),
+    Float(f64),
     Boolean(bool),
+    Point(Point),
 }
 
+/// A point value, eg. a point of a CURVE payload
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum ObjectTypes {
     PROGRAM,
@@ -178,8 +293,8 @@ impl ObjectTypes {
 #[serde(rename_all = "camelCase")]
 pub struct Subscription {
     pub id: Option<String>,
-    pub created_date_time: Option<String>,
-    pub modification_date_time: Option<String>,
+    pub created_date_time: Option<Timestamp>,
+    pub modification_date_time: Option<Timestamp>,
     pub object_type: Option<ObjectTypes>,
     pub client_name: String,
     #[serde(rename = "programID")]
@@ -203,7 +318,7 @@ pub struct Operations {
 }
 
 /// Possible subscription operation types
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum Operation {
     GET,
@@ -211,3 +326,299 @@ pub enum Operation {
     PUT,
     DELETE,
 }
+
+/// OpenADR 3.0 Program Model
+///
+/// Provides program specific metadata from VTN to VEN. Events and subscriptions reference programs with `programID`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Program {
+    /// VTN provisioned ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// VTN Provisioned on creation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_date_time: Option<Timestamp>,
+    /// VTN Provisioned on modification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modification_date_time: Option<Timestamp>,
+    /// Used as discriminator, eg. notification object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_type: Option<ObjectTypes>,
+    /// Short name to uniquely identify the program
+    pub program_name: String,
+    /// Long name of the program for human readability
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program_long_name: Option<String>,
+    /// Interval period defining the temporal extent of the program
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_period: Option<IntervalPeriod>,
+    /// Human or machine readable descriptions of the program
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program_descriptions: Option<Vec<ProgramDescription>>,
+    /// True if events are fixed once transmitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binding_events: Option<bool>,
+    /// True if events have been adapted from a grid event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_price: Option<bool>,
+    /// Payload descriptors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_descriptors: Option<Vec<EventPayloadDescriptor>>,
+    /// Targets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub targets: Option<Vec<ValuesMap>>,
+}
+
+/// Program description, a link to human or machine readable documentation of the program
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProgramDescription {
+    /// A human or machine readable program description
+    #[serde(rename = "URL")]
+    pub url: String,
+}
+
+/// OpenADR 3.0 Report Model
+///
+/// Report object sent by the VEN to the VTN, eg. as a response to the report descriptors of an event.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    /// VTN provisioned ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// VTN Provisioned on creation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_date_time: Option<Timestamp>,
+    /// VTN Provisioned on modification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modification_date_time: Option<Timestamp>,
+    /// Used as discriminator, eg. notification object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_type: Option<ObjectTypes>,
+    /// Program ID of the program the report relates to
+    #[serde(rename = "programID")]
+    pub program_id: String,
+    /// Event ID of the event that requested the report
+    #[serde(rename = "eventID")]
+    pub event_id: String,
+    /// User generated identifier, eg. the VEN name
+    pub client_name: String,
+    /// Report name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_name: Option<String>,
+    /// Payload descriptors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_descriptors: Option<Vec<ReportPayloadDescriptor>>,
+    /// Report data per resource
+    pub resources: Vec<ReportResource>,
+}
+
+/// Describes the payloads of the report intervals
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportPayloadDescriptor {
+    /// Object type discriminator
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_type: Option<PayloadDescriptorType>, //always report here
+    /// Payload type - Example: USAGE
+    pub payload_type: String,
+    /// Reading type - Example: DIRECT_READ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reading_type: Option<String>,
+    /// Unit of measure - Example: kWh
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units: Option<String>,
+    /// Accuracy - A quantification of the accuracy of the set of data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accuracy: Option<f64>,
+    /// Confidence - A quantification of the confidence in the set of data, 0-100
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<i64>,
+}
+
+/// Report data for a single resource
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportResource {
+    /// Resource name, matching the RESOURCE_NAME target of the event
+    pub resource_name: String,
+    /// Interval Period
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_period: Option<IntervalPeriod>,
+    /// Intervals
+    pub intervals: Vec<Interval>,
+}
+
+/// OpenADR 3.0 Notification Model
+///
+/// VTN generated object sent to the callback URL of a subscription when an object the subscription is interested in
+/// is created, updated or deleted.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    /// Type of the object in the notification
+    pub object_type: ObjectTypes,
+    /// The operation on the object that triggered the notification
+    pub operation: Operation,
+    /// Targets of the object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub targets: Option<Vec<ValuesMap>>,
+    /// The object that is the subject of the notification
+    pub object: NotificationObject,
+}
+
+/// Objects that can be sent in a notification
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum NotificationObject {
+    Event(OpenADREvent),
+    Program(Program),
+    Report(Report),
+    Subscription(Subscription),
+    Ven(Ven),
+    Resource(Resource),
+}
+
+/// OpenADR 3.0 VEN Model
+///
+/// Represents a VEN registered to the VTN. The VTN provisions the ID on registration.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Ven {
+    /// VTN provisioned ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// VTN Provisioned on creation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_date_time: Option<Timestamp>,
+    /// VTN Provisioned on modification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modification_date_time: Option<Timestamp>,
+    /// Used as discriminator, eg. notification object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_type: Option<ObjectTypes>,
+    /// User generated identifier, may be VEN identifier provisioned out-of-band
+    pub ven_name: String,
+    /// Attributes of the VEN, eg. location
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Vec<ValuesMap>>,
+    /// Targets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub targets: Option<Vec<ValuesMap>>,
+}
+
+/// OpenADR 3.0 Resource Model
+///
+/// Represents a resource of a VEN, eg. a charger. Events target resources with the RESOURCE_NAME target type.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    /// VTN provisioned ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// VTN Provisioned on creation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_date_time: Option<Timestamp>,
+    /// VTN Provisioned on modification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modification_date_time: Option<Timestamp>,
+    /// Used as discriminator, eg. notification object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_type: Option<ObjectTypes>,
+    /// User generated identifier, matched against the RESOURCE_NAME targets of events
+    pub resource_name: String,
+    /// ID of the VEN the resource belongs to, VTN provisioned from the path
+    #[serde(rename = "venID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ven_id: Option<String>,
+    /// Attributes of the resource, eg. max power
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Vec<ValuesMap>>,
+    /// Targets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub targets: Option<Vec<ValuesMap>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_payload_type_names() {
+        // The names of the generated payloads are the names given to the event generator
+        for name in [
+            "SIMPLE",
+            "PRICE",
+            "CHARGE_STATE_SETPOINT",
+            "DISPATCH_SETPOINT",
+            "DISPATCH_SETPOINT_RELATIVE",
+            "CONTROL_SETPOINT",
+            "EXPORT_PRICE",
+            "GHG",
+            "CURVE",
+            "OLS",
+            "IMPORT_CAPACITY_SUBSCRIPTION",
+            "IMPORT_CAPACITY_RESERVATION",
+            "IMPORT_CAPACITY_RESERVATION_FEE",
+            "IMPORT_CAPACITY_AVAILABLE",
+            "IMPORT_CAPACITY_AVAILABLE_PRICE",
+            "EXPORT_CAPACITY_SUBSCRIPTION",
+            "EXPORT_CAPACITY_RESERVATION",
+            "EXPORT_CAPACITY_RESERVATION_FEE",
+            "EXPORT_CAPACITY_AVAILABLE",
+            "EXPORT_CAPACITY_AVAILABLE_PRICE",
+            "IMPORT_CAPACITY_LIMIT",
+            "EXPORT_CAPACITY_LIMIT",
+            "ALERT_GRID_EMERGENCY",
+            "ALERT_BLACK_START",
+            "ALERT_POSSIBLE_OUTAGE",
+            "ALERT_FLEX_ALERT",
+            "ALERT_FIRE",
+            "ALERT_FREEZING",
+            "ALERT_WIND",
+            "ALERT_TSUNAMI",
+            "ALERT_AIR_QUALITY",
+            "ALERT_OTHER",
+            "CTA2045_REBOOT",
+            "CTA2045_SET_OVERRIDE_STATUS",
+        ] {
+            let payload_type: EventPayloadType =
+                serde_json::from_value(serde_json::json!(name)).unwrap();
+            assert_eq!(payload_type.name(), name);
+        }
+    }
+
+    #[test]
+    fn test_values_round_trip() {
+        let json = r#"["PRICE",5,-3,0.153,1.0,1e-7,true,{"x":0.5,"y":-12.0}]"#;
+        let values: Vec<Values> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Values::String("PRICE".to_string()),
+                Values::Integer(5),
+                Values::Integer(-3),
+                Values::Float(0.153),
+                Values::Float(1.0),
+                Values::Float(1e-7),
+                Values::Boolean(true),
+                Values::Point(Point { x: 0.5, y: -12.0 }),
+            ]
+        );
+
+        // Serializing the parsed values gives back the same values
+        let serialized = serde_json::to_string(&values).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<Values>>(&serialized).unwrap(),
+            values
+        );
+        assert_eq!(
+            serialized,
+            r#"["PRICE",5,-3,0.153,1.0,1e-7,true,{"x":0.5,"y":-12.0}]"#
+        );
+
+        // Objects other than points are rejected
+        assert!(serde_json::from_str::<Values>(r#"{"x":1,"z":2}"#).is_err());
+    }
+}
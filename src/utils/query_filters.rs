@@ -0,0 +1,256 @@
+use crate::utils::openadr_models::{Values, ValuesMap};
+
+/// Query parameters supported by the OpenADR 3.0 list endpoints
+///
+/// Parsed from the raw query string pairs so that `targetValues` can be given multiple times, eg.
+/// `?targetType=RESOURCE_NAME&targetValues=charger1&targetValues=charger2`. Comma separated values are also accepted.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct QueryFilters {
+    /// Only return objects with this program ID
+    pub program_id: Option<String>,
+    /// Only return VENs with this name
+    pub ven_name: Option<String>,
+    /// Target type the target values are matched against, eg. RESOURCE_NAME
+    pub target_type: Option<String>,
+    /// Target values, an object matches if any of its values of the target type is in this list
+    pub target_values: Vec<String>,
+    /// Number of matching objects to skip
+    pub skip: usize,
+    /// Maximum number of objects to return, all matching objects are returned if not set
+    pub limit: Option<usize>,
+}
+
+impl QueryFilters {
+    /// Parse the query filters from the query string key value pairs
+    ///
+    /// Unknown parameters are ignored.
+    ///
+    /// # Parameters
+    /// - `pairs`: The key value pairs of the query string
+    ///
+    /// # Returns
+    /// - `Result<QueryFilters, String>`: The parsed filters, or a description of the invalid parameter
+    pub fn from_pairs(pairs: Vec<(String, String)>) -> Result<QueryFilters, String> {
+        let mut filters = QueryFilters::default();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "programID" => filters.program_id = Some(value),
+                "venName" => filters.ven_name = Some(value),
+                "targetType" => filters.target_type = Some(value),
+                "targetValues" => filters.target_values.extend(
+                    value
+                        .split(',')
+                        .filter(|value| !value.is_empty())
+                        .map(|value| value.to_string()),
+                ),
+                "skip" => {
+                    filters.skip = value
+                        .parse()
+                        .map_err(|_| format!("Invalid skip: {}", value))?
+                }
+                "limit" => {
+                    filters.limit = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid limit: {}", value))?,
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        if filters.target_type.is_none() && !filters.target_values.is_empty() {
+            return Err("targetValues requires targetType".to_string());
+        }
+
+        Ok(filters)
+    }
+
+    /// Check whether an object with the given program ID passes the program filter
+    pub fn matches_program(&self, program_id: &str) -> bool {
+        match &self.program_id {
+            Some(filter) => filter == program_id,
+            None => true,
+        }
+    }
+
+    /// Check whether a VEN with the given name passes the VEN name filter
+    pub fn matches_ven_name(&self, ven_name: &str) -> bool {
+        match &self.ven_name {
+            Some(filter) => filter == ven_name,
+            None => true,
+        }
+    }
+
+    /// Check whether an object with the given targets passes the target filter
+    ///
+    /// Objects without any targets apply to everyone and always pass. Otherwise the object needs a target of the
+    /// filtered type, and if target values are given, at least one of its values has to be in the filter. Numeric values
+    /// are compared numerically, so that eg. `1.0` matches the integer `1`.
+    pub fn matches_targets(&self, targets: &Option<Vec<ValuesMap>>) -> bool {
+        let target_type = match &self.target_type {
+            Some(target_type) => target_type,
+            None => return true,
+        };
+        let targets = match targets {
+            Some(targets) if !targets.is_empty() => targets,
+            _ => return true,
+        };
+
+        targets
+            .iter()
+            .filter(|target| &target.kind == target_type)
+            .any(|target| {
+                self.target_values.is_empty()
+                    || target.values.iter().any(|value| match value {
+                        Values::String(value) => self.target_values.contains(value),
+                        Values::Boolean(value) => self.target_values.contains(&value.to_string()),
+                        Values::Integer(value) => self
+                            .target_values
+                            .iter()
+                            .any(|filter| integer_matches(filter, *value)),
+                        Values::Float(value) => self.target_values.iter().any(|filter| {
+                            filter.parse::<f64>().is_ok_and(|filter| filter == *value)
+                        }),
+                        // Points can't be given as query parameters
+                        Values::Point(_) => false,
+                    })
+            })
+    }
+
+    /// Apply skip and limit to the already filtered objects
+    pub fn paginate<T>(&self, objects: Vec<T>) -> Vec<T> {
+        let objects = objects.into_iter().skip(self.skip);
+        match self.limit {
+            Some(limit) => objects.take(limit).collect(),
+            None => objects.collect(),
+        }
+    }
+}
+
+/// Largest magnitude up to which every integer has an exact floating point representation, 2^53
+const MAX_EXACT_FLOAT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Check whether a target value filter matches an integer value
+///
+/// Integer filters are compared exactly, as strings if they don't fit in i64, so that large IDs aren't rounded.
+/// Decimal filters, eg. `1.0`, are compared numerically within the range where floats represent integers exactly.
+fn integer_matches(filter: &str, value: i64) -> bool {
+    if let Ok(filter) = filter.parse::<i64>() {
+        return filter == value;
+    }
+    let digits = filter.strip_prefix(['-', '+']).unwrap_or(filter);
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        return filter == value.to_string();
+    }
+    filter
+        .parse::<f64>()
+        .is_ok_and(|filter| filter.abs() < MAX_EXACT_FLOAT_INTEGER && filter == value as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(query: &[(&str, &str)]) -> Vec<(String, String)> {
+        query
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn resource_target(names: &[&str]) -> Option<Vec<ValuesMap>> {
+        Some(vec![ValuesMap {
+            kind: "RESOURCE_NAME".to_string(),
+            values: names
+                .iter()
+                .map(|name| Values::String(name.to_string()))
+                .collect(),
+        }])
+    }
+
+    #[test]
+    fn test_from_pairs() {
+        let filters = QueryFilters::from_pairs(pairs(&[
+            ("programID", "1"),
+            ("targetType", "RESOURCE_NAME"),
+            ("targetValues", "charger1"),
+            ("targetValues", "charger2,charger3"),
+            ("skip", "2"),
+            ("limit", "10"),
+            ("unknown", "ignored"),
+        ]))
+        .unwrap();
+
+        assert_eq!(filters.program_id, Some("1".to_string()));
+        assert_eq!(filters.target_type, Some("RESOURCE_NAME".to_string()));
+        assert_eq!(
+            filters.target_values,
+            vec!["charger1", "charger2", "charger3"]
+        );
+        assert_eq!(filters.skip, 2);
+        assert_eq!(filters.limit, Some(10));
+
+        assert!(QueryFilters::from_pairs(pairs(&[("skip", "-1")])).is_err());
+        assert!(QueryFilters::from_pairs(pairs(&[("limit", "abc")])).is_err());
+        assert!(QueryFilters::from_pairs(pairs(&[("targetValues", "charger1")])).is_err());
+    }
+
+    #[test]
+    fn test_matches_targets() {
+        let filters = QueryFilters::from_pairs(pairs(&[
+            ("targetType", "RESOURCE_NAME"),
+            ("targetValues", "charger1"),
+        ]))
+        .unwrap();
+
+        assert!(filters.matches_targets(&resource_target(&["charger1", "charger2"])));
+        assert!(!filters.matches_targets(&resource_target(&["charger2"])));
+        assert!(filters.matches_targets(&None));
+        assert!(QueryFilters::default().matches_targets(&resource_target(&["charger2"])));
+
+        // Numeric target values are compared numerically
+        let filters =
+            QueryFilters::from_pairs(pairs(&[("targetType", "LEVEL"), ("targetValues", "0.5")]))
+                .unwrap();
+        let level_target = |value: Values| {
+            Some(vec![ValuesMap {
+                kind: "LEVEL".to_string(),
+                values: vec![value],
+            }])
+        };
+        assert!(filters.matches_targets(&level_target(Values::Float(0.5))));
+        assert!(!filters.matches_targets(&level_target(Values::Integer(5))));
+        let filters = QueryFilters::from_pairs(pairs(&[
+            ("targetType", "LEVEL"),
+            ("targetValues", "1.0"),
+            ("targetValues", "7"),
+        ]))
+        .unwrap();
+        assert!(filters.matches_targets(&level_target(Values::Float(1.0))));
+        assert!(filters.matches_targets(&level_target(Values::Integer(1))));
+        assert!(filters.matches_targets(&level_target(Values::Float(7.0))));
+        assert!(!filters.matches_targets(&level_target(Values::Float(1.5))));
+        assert!(!filters.matches_targets(&level_target(Values::String("1".to_string()))));
+
+        // Integers outside of the exact float range aren't rounded
+        let filters = QueryFilters::from_pairs(pairs(&[
+            ("targetType", "LEVEL"),
+            ("targetValues", "9223372036854775808"),
+            ("targetValues", "9007199254740992.0"),
+        ]))
+        .unwrap();
+        assert!(!filters.matches_targets(&level_target(Values::Integer(i64::MAX))));
+        assert!(!filters.matches_targets(&level_target(Values::Integer(9007199254740992))));
+        assert!(!filters.matches_targets(&level_target(Values::Integer(9007199254740993))));
+        assert!(integer_matches("-5", -5));
+        assert!(integer_matches("-5.0", -5));
+    }
+
+    #[test]
+    fn test_paginate() {
+        let filters = QueryFilters::from_pairs(pairs(&[("skip", "1"), ("limit", "2")])).unwrap();
+        assert_eq!(filters.paginate(vec![1, 2, 3, 4]), vec![2, 3]);
+        assert_eq!(QueryFilters::default().paginate(vec![1, 2]), vec![1, 2]);
+    }
+}
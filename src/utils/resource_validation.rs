@@ -0,0 +1,155 @@
+use crate::utils::openadr_models::{Values, ValuesMap};
+use crate::AppState;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use log::warn;
+use shuttle_runtime::SecretStore;
+
+/// Header listing the unregistered resources when the policy is to flag them
+pub const UNREGISTERED_RESOURCES_HEADER: &str = "X-Unregistered-Resources";
+
+/// How to handle RESOURCE_NAME targets that reference resources not registered under any VEN
+///
+/// Configured with the optional `UNREGISTERED_RESOURCE_POLICY` secret, one of `allow`, `flag` or `reject`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnregisteredResourcePolicy {
+    /// Accept any resource name, the default
+    Allow,
+    /// Accept the request, but list the unregistered resources in the `X-Unregistered-Resources` response header
+    Flag,
+    /// Reject the request with 400 Bad Request
+    Reject,
+}
+
+impl UnregisteredResourcePolicy {
+    /// Read the policy from the secrets, defaults to allow
+    pub fn from_secrets(secrets: &SecretStore) -> UnregisteredResourcePolicy {
+        match secrets.get("UNREGISTERED_RESOURCE_POLICY").as_deref() {
+            Some("flag") => UnregisteredResourcePolicy::Flag,
+            Some("reject") => UnregisteredResourcePolicy::Reject,
+            _ => UnregisteredResourcePolicy::Allow,
+        }
+    }
+}
+
+/// Collect the resource names of the RESOURCE_NAME targets
+pub fn resource_names(targets: &Option<Vec<ValuesMap>>) -> Vec<String> {
+    targets
+        .iter()
+        .flatten()
+        .filter(|target| target.kind == "RESOURCE_NAME")
+        .flat_map(|target| &target.values)
+        .filter_map(|value| match value {
+            Values::String(name) => Some(name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Validate that the given resource names are registered under a VEN, according to the configured policy
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `resource_names`: The resource names referenced by the request
+///
+/// # Returns
+/// - `Result<HeaderMap, (StatusCode, String)>`: The headers to add to the response, or an error if unregistered
+///   resources are rejected
+pub fn validate_resource_names(
+    state: &AppState,
+    resource_names: &[String],
+) -> Result<HeaderMap, (StatusCode, String)> {
+    let mut headers = HeaderMap::new();
+
+    let policy = UnregisteredResourcePolicy::from_secrets(&state.secrets);
+    if policy == UnregisteredResourcePolicy::Allow {
+        return Ok(headers);
+    }
+
+    let unregistered_names: Vec<&str> = resource_names
+        .iter()
+        .filter(|name| {
+            !state
+                .resources
+                .iter()
+                .any(|resource| &resource.resource_name == *name)
+        })
+        .map(|name| name.as_str())
+        .collect();
+    if unregistered_names.is_empty() {
+        return Ok(headers);
+    }
+
+    let unregistered = unregistered_names.join(",");
+    warn!("Unregistered resources referenced: {}", unregistered);
+    if policy == UnregisteredResourcePolicy::Reject {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unregistered resources: {}", unregistered),
+        ));
+    }
+
+    // Header values are limited to visible ASCII, so the names are percent-encoded
+    let encoded: Vec<String> = unregistered_names
+        .iter()
+        .map(|name| percent_encode(name))
+        .collect();
+    let value = HeaderValue::from_str(&encoded.join(",")).map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Invalid {} header: {}", UNREGISTERED_RESOURCES_HEADER, err),
+        )
+    })?;
+    headers.insert(UNREGISTERED_RESOURCES_HEADER, value);
+    Ok(headers)
+}
+
+/// Percent-encode a resource name for a header value
+///
+/// Every byte except visible ASCII is encoded, along with `%` and the `,` separating the names.
+fn percent_encode(name: &str) -> String {
+    name.bytes()
+        .map(|byte| match byte {
+            b'%' | b',' => format!("%{:02X}", byte),
+            byte if byte.is_ascii_graphic() => (byte as char).to_string(),
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_helpers::test_state;
+
+    #[test]
+    fn test_resource_names() {
+        let targets = Some(vec![
+            ValuesMap {
+                kind: "RESOURCE_NAME".to_string(),
+                values: vec![
+                    Values::String("charger1".to_string()),
+                    Values::String("charger2".to_string()),
+                ],
+            },
+            ValuesMap {
+                kind: "ORGANIZATION_ID".to_string(),
+                values: vec![Values::String("TestVTN".to_string())],
+            },
+        ]);
+
+        assert_eq!(resource_names(&targets), vec!["charger1", "charger2"]);
+        assert!(resource_names(&None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_validate_resource_names_flag() {
+        let state = test_state(&[("UNREGISTERED_RESOURCE_POLICY", "flag")]).await;
+        let names = vec!["laturi Öljy".to_string(), "a,b%".to_string()];
+
+        let headers = validate_resource_names(&state, &names).unwrap();
+        assert_eq!(
+            headers.get(UNREGISTERED_RESOURCES_HEADER).unwrap(),
+            "laturi%20%C3%96ljy,a%2Cb%25"
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use crate::utils::authorizer::{authenticated_client, has_admin_token, has_credentials};
+use crate::utils::iso8601::Timestamp;
+use crate::utils::oauth_clients::ClientRole;
+use crate::utils::tokens::Scope;
+use crate::AppState;
+use axum::http::{HeaderMap, StatusCode};
+use log::debug;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Header selecting the test session of a request
+pub const SESSION_HEADER: &str = "X-Test-Session";
+
+/// Test session, an isolated namespace of the VTN
+///
+/// Each session has its own events, subscriptions, programs, reports, VENs, resources and delivery log, so that
+/// concurrent test suites don't interfere with each other.
+#[derive(Clone)]
+pub struct TestSession {
+    /// Creation timestamp of the session
+    pub created_date_time: Timestamp,
+    /// Clients bound to the session. Requests of these clients use the session without the session header.
+    pub clients: Vec<String>,
+    /// The isolated state of the session
+    pub state: Arc<AppState>,
+}
+
+/// Summary of a test session returned by the session admin endpoints
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub id: String,
+    pub created_date_time: Timestamp,
+    pub clients: Vec<String>,
+    pub event_count: usize,
+    pub subscription_count: usize,
+    pub delivery_count: usize,
+}
+
+impl TestSession {
+    /// Summarize the session
+    ///
+    /// # Parameters
+    /// - `id`: The ID of the session
+    ///
+    /// # Returns
+    /// - `SessionInfo`: The summary of the session
+    pub async fn info(&self, id: &str) -> SessionInfo {
+        SessionInfo {
+            id: id.to_string(),
+            created_date_time: self.created_date_time,
+            clients: self.clients.clone(),
+            event_count: self.state.event_storage.read().await.len(),
+            subscription_count: self.state.subscriptions.len(),
+            delivery_count: self.state.delivery_log.read().await.len(),
+        }
+    }
+}
+
+/// Check that a session ID can be used in the session header
+///
+/// Session IDs are 1-64 characters of ASCII letters, digits, `-` and `_`.
+pub fn valid_session_id(session_id: &str) -> bool {
+    !session_id.is_empty()
+        && session_id.len() <= 64
+        && session_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Select the state used by a request
+///
+/// The session is selected by the `X-Test-Session` header, or by the session the calling client is bound to. Requests
+/// without a session use the state of the application. Sessions with bound clients can only be selected with the header
+/// by the bound clients and admins, see [`may_use_session`].
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `header_map`: The headers of the request
+///
+/// # Returns
+/// - `Result<Arc<AppState>, (StatusCode, String)>`: The state of the selected session, or 404 Not Found if the session
+///   doesn't exist or the caller may not use it. Requests without valid credentials get 401 Unauthorized instead, so
+///   that the session IDs aren't revealed before authentication
+pub fn session_state(
+    state: &Arc<AppState>,
+    header_map: &HeaderMap,
+) -> Result<Arc<AppState>, (StatusCode, String)> {
+    if let Some(session_id) = header_map.get(SESSION_HEADER) {
+        let session_id = session_id.to_str().unwrap_or_default();
+        return match state.sessions.get(session_id) {
+            Some(session) if may_use_session(state, header_map, &session) => {
+                Ok(session.state.clone())
+            }
+            _ if !has_credentials(state, header_map) => {
+                debug!("Invalid auth header for test session {:?}", session_id);
+                Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()))
+            }
+            _ => {
+                debug!("Test session {:?} not found", session_id);
+                Err((StatusCode::NOT_FOUND, "Test session not found".to_string()))
+            }
+        };
+    }
+
+    if let Some(token) = authenticated_client(state, header_map) {
+        let bound = state
+            .sessions
+            .iter()
+            .find(|session| session.clients.contains(&token.client_id))
+            .map(|session| session.state.clone());
+        if let Some(session_state) = bound {
+            return Ok(session_state);
+        }
+    }
+
+    Ok(state.clone())
+}
+
+/// Check whether a request may select a session with the session header
+///
+/// Sessions without bound clients can be used by everyone. Sessions with bound clients isolate the test runs of the
+/// clients, so they can only be used by the bound clients, the admin token, BL clients and clients with the read_all
+/// scope.
+fn may_use_session(state: &AppState, header_map: &HeaderMap, session: &TestSession) -> bool {
+    if session.clients.is_empty() || has_admin_token(state, header_map) {
+        return true;
+    }
+    authenticated_client(state, header_map).is_some_and(|token| {
+        token.role == ClientRole::BL
+            || token.has_scope(Scope::ReadAll)
+            || session.clients.contains(&token.client_id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::init_storage::{dummy_event_to_storage, init_session_storage, init_storage};
+    use crate::utils::oauth_clients::{ClientRole, OAuthClient};
+    use crate::utils::tokens::issue_token;
+    use axum::http::HeaderValue;
+    use shuttle_runtime::SecretStore;
+    use std::collections::BTreeMap;
+
+    fn session(state: &AppState, clients: Vec<String>) -> TestSession {
+        TestSession {
+            created_date_time: Timestamp::now(),
+            clients,
+            state: init_session_storage(state),
+        }
+    }
+
+    #[test]
+    fn test_valid_session_id() {
+        assert!(valid_session_id("suite-a_1"));
+        assert!(!valid_session_id(""));
+        assert!(!valid_session_id("suite a"));
+        assert!(!valid_session_id(&"a".repeat(65)));
+    }
+
+    #[tokio::test]
+    async fn test_session_state() {
+        let state = init_storage(SecretStore::new(BTreeMap::new())).await;
+        dummy_event_to_storage(&state).await;
+        state
+            .sessions
+            .insert("suite-a".to_string(), session(&state, vec![]));
+
+        // Without a session the application state is used
+        let selected = session_state(&state, &HeaderMap::new()).unwrap();
+        assert!(Arc::ptr_eq(&selected, &state));
+
+        // The session has its own storage
+        let mut header_map = HeaderMap::new();
+        header_map.insert(SESSION_HEADER, HeaderValue::from_static("suite-a"));
+        let selected = session_state(&state, &header_map).unwrap();
+        assert!(!Arc::ptr_eq(&selected, &state));
+        assert!(selected.event_storage.read().await.is_empty());
+        assert!(selected.sessions.is_empty());
+
+        // Unknown sessions are rejected, after authentication
+        header_map.insert(SESSION_HEADER, HeaderValue::from_static("suite-b"));
+        assert_eq!(
+            session_state(&state, &header_map)
+                .map(|_| ())
+                .unwrap_err()
+                .0,
+            StatusCode::UNAUTHORIZED
+        );
+        let client = OAuthClient {
+            client_id: "ven1".to_string(),
+            client_secret: "secret".to_string(),
+            scopes: None,
+            role: ClientRole::VEN,
+        };
+        let token = issue_token(&state, &client, None);
+        header_map.insert(
+            "Authorization",
+            HeaderValue::from_str(format!("Bearer {}", token.access_token).as_str()).unwrap(),
+        );
+        assert_eq!(
+            session_state(&state, &header_map)
+                .map(|_| ())
+                .unwrap_err()
+                .0,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_state_bound_client() {
+        let state = init_storage(SecretStore::new(BTreeMap::new())).await;
+        state.sessions.insert(
+            "suite-a".to_string(),
+            session(&state, vec!["ven1".to_string()]),
+        );
+        let client = OAuthClient {
+            client_id: "ven1".to_string(),
+            client_secret: "secret".to_string(),
+            scopes: None,
+            role: ClientRole::VEN,
+        };
+
+        // Tokens are shared, so the token of a bound client selects its session
+        let token = issue_token(&state, &client, None);
+        let mut header_map = HeaderMap::new();
+        header_map.insert(
+            "Authorization",
+            HeaderValue::from_str(format!("Bearer {}", token.access_token).as_str()).unwrap(),
+        );
+        let selected = session_state(&state, &header_map).unwrap();
+        let bound_state = state.sessions.get("suite-a").unwrap().state.clone();
+        assert!(Arc::ptr_eq(&selected, &bound_state));
+        assert!(authenticated_client(&selected, &header_map).is_some());
+
+        // Other clients use the application state
+        let other = issue_token(
+            &state,
+            &OAuthClient {
+                client_id: "ven2".to_string(),
+                ..client
+            },
+            None,
+        );
+        header_map.insert(
+            "Authorization",
+            HeaderValue::from_str(format!("Bearer {}", other.access_token).as_str()).unwrap(),
+        );
+        assert!(Arc::ptr_eq(
+            &session_state(&state, &header_map).unwrap(),
+            &state
+        ));
+
+        // Other clients can't select the session with the header, unless they have the read_all scope
+        header_map.insert(SESSION_HEADER, HeaderValue::from_static("suite-a"));
+        assert!(session_state(&state, &header_map).is_ok());
+        let other = issue_token(
+            &state,
+            &OAuthClient {
+                client_id: "ven2".to_string(),
+                client_secret: "secret".to_string(),
+                scopes: None,
+                role: ClientRole::VEN,
+            },
+            Some("read_targets".to_string()),
+        );
+        header_map.insert(
+            "Authorization",
+            HeaderValue::from_str(format!("Bearer {}", other.access_token).as_str()).unwrap(),
+        );
+        assert_eq!(
+            session_state(&state, &header_map)
+                .map(|_| ())
+                .unwrap_err()
+                .0,
+            StatusCode::NOT_FOUND
+        );
+
+        // The bound client and admins can
+        for token in [
+            token.access_token,
+            issue_token(
+                &state,
+                &OAuthClient {
+                    client_id: "bl".to_string(),
+                    client_secret: "secret".to_string(),
+                    scopes: None,
+                    role: ClientRole::BL,
+                },
+                Some("read_targets".to_string()),
+            )
+            .access_token,
+        ] {
+            header_map.insert(
+                "Authorization",
+                HeaderValue::from_str(format!("Bearer {}", token).as_str()).unwrap(),
+            );
+            assert!(Arc::ptr_eq(
+                &session_state(&state, &header_map).unwrap(),
+                &bound_state
+            ));
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::utils::init_storage::init_storage;
+use crate::utils::oauth_clients::{ClientRole, OAuthClient};
+use crate::utils::openadr_models::{
+    ObjectOperation, ObjectTypes, Operation, Operations, Subscription,
+};
+use crate::utils::tokens::issue_token;
+use crate::AppState;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::Json;
+use shuttle_common::Secret;
+use shuttle_runtime::SecretStore;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Application state with empty storage, built from the given secrets
+pub async fn test_state(secrets: &[(&str, &str)]) -> Arc<AppState> {
+    let mut secrets_tree: BTreeMap<String, Secret<String>> = BTreeMap::new();
+    for (key, value) in secrets {
+        secrets_tree.insert(key.to_string(), Secret::new(value.to_string()));
+    }
+    init_storage(SecretStore::new(secrets_tree)).await
+}
+
+/// Headers with a bearer token issued to the client
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `client_id`: The ID of the client the token is issued to
+/// - `role`: The role of the client
+/// - `scope`: The scope of the token, every scope if None
+pub fn bearer(
+    state: &AppState,
+    client_id: &str,
+    role: ClientRole,
+    scope: Option<&str>,
+) -> HeaderMap {
+    let client = OAuthClient {
+        client_id: client_id.to_string(),
+        client_secret: "secret".to_string(),
+        scopes: None,
+        role,
+    };
+    let token = issue_token(state, &client, scope.map(|scope| scope.to_string()));
+    let mut header_map = HeaderMap::new();
+    header_map.insert(
+        "Authorization",
+        HeaderValue::from_str(format!("Bearer {}", token.access_token).as_str()).unwrap(),
+    );
+    header_map
+}
+
+/// Callback URL nothing listens on, deliveries to it fail right away
+pub const UNREACHABLE_CALLBACK_URL: &str = "http://127.0.0.1:9/";
+
+/// Start a VEN callback server on a random local port
+///
+/// The server responds to every request with the given status code and records the received JSON bodies.
+///
+/// # Returns
+/// - `(String, Arc<Mutex<Vec<serde_json::Value>>>)`: The callback URL and the received bodies
+pub async fn callback_server(status: StatusCode) -> (String, Arc<Mutex<Vec<serde_json::Value>>>) {
+    let received: Arc<Mutex<Vec<serde_json::Value>>> = Arc::new(Mutex::new(Vec::new()));
+    let bodies = received.clone();
+    let router = axum::Router::new().fallback(move |body: Json<serde_json::Value>| async move {
+        bodies.lock().unwrap().push(body.0);
+        status
+    });
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let callback_url = format!("http://{}/callback", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    (callback_url, received)
+}
+
+/// Store a subscription to every operation of the object types with one object operation per callback URL
+pub fn store_subscription(
+    state: &AppState,
+    subscription_id: &str,
+    object_type: ObjectTypes,
+    callback_urls: &[&str],
+) {
+    let object_operations = callback_urls
+        .iter()
+        .map(|callback_url| ObjectOperation {
+            object_type: vec![object_type.clone()],
+            operations: Operations {
+                operations: vec![Operation::POST, Operation::PUT, Operation::DELETE],
+            },
+            callback_url: callback_url.to_string(),
+            bearer_token: "token".to_string(),
+        })
+        .collect();
+    state.subscriptions.insert(
+        subscription_id.to_string(),
+        Subscription {
+            id: Some(subscription_id.to_string()),
+            created_date_time: None,
+            modification_date_time: None,
+            object_type: None,
+            client_name: "ven1".to_string(),
+            program_id: "1".to_string(),
+            object_operations,
+            targets: None,
+        },
+    );
+}
@@ -0,0 +1,418 @@
+use crate::utils::oauth_clients::{decode_basic_auth_header, OAuthClient};
+use axum::extract::{FromRequest, Multipart, Request};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{Form, Json};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Body of an OAuth2 token request
+///
+/// Unknown fields are rejected, as the request should only contain the grant type, scope and optionally the client
+/// credentials.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenRequest {
+    /// Grant type, should be client_credentials
+    pub grant_type: Option<String>,
+    /// Requested scope
+    pub scope: Option<String>,
+    /// Client ID, when the client authenticates with body credentials instead of the Basic auth header
+    pub client_id: Option<String>,
+    /// Client secret, when the client authenticates with body credentials instead of the Basic auth header
+    pub client_secret: Option<String>,
+}
+
+/// Error codes of the token endpoint as specified by RFC 6749 section 5.2
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenErrorCode {
+    /// The request is missing a parameter, has an unsupported body or is otherwise malformed
+    InvalidRequest,
+    /// Client authentication failed
+    InvalidClient,
+    /// The grant type is not client_credentials
+    UnsupportedGrantType,
+    /// The requested scope is invalid
+    InvalidScope,
+}
+
+/// Error response of the token endpoint as specified by RFC 6749 section 5.2
+///
+/// Responds with 401 Unauthorized and a `WWW-Authenticate` header for `invalid_client`, otherwise with 400 Bad Request.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenError {
+    /// Error code
+    pub error: TokenErrorCode,
+    /// Human readable description of the error
+    pub error_description: String,
+}
+
+impl TokenError {
+    /// Create a new token error
+    pub fn new(error: TokenErrorCode, error_description: &str) -> TokenError {
+        TokenError {
+            error,
+            error_description: error_description.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for TokenError {
+    fn into_response(self) -> Response {
+        let mut headers = HeaderMap::new();
+        let status = match self.error {
+            TokenErrorCode::InvalidClient => {
+                headers.insert(
+                    WWW_AUTHENTICATE,
+                    HeaderValue::from_static("Basic realm=\"openadr_test_vtn\""),
+                );
+                StatusCode::UNAUTHORIZED
+            }
+            _ => StatusCode::BAD_REQUEST,
+        };
+        (status, headers, Json(self)).into_response()
+    }
+}
+
+/// Parse the token request body according to its content type
+///
+/// Supports `application/x-www-form-urlencoded` as specified by RFC 6749, `multipart/form-data` and `application/json`.
+///
+/// # Parameters
+/// - `request`: The token request
+///
+/// # Returns
+/// - `Result<TokenRequest, TokenError>`: The parsed body, or an error if the body is invalid
+pub async fn parse_token_request(request: Request) -> Result<TokenRequest, TokenError> {
+    let content_type = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+    debug!("Token request content type: {}", content_type);
+
+    if content_type.starts_with("application/x-www-form-urlencoded") {
+        let Form(body) = Form::<TokenRequest>::from_request(request, &())
+            .await
+            .map_err(|err| {
+                TokenError::new(
+                    TokenErrorCode::InvalidRequest,
+                    &format!("Invalid form data: {}", err),
+                )
+            })?;
+        return Ok(body);
+    }
+    if content_type.starts_with("application/json") {
+        let Json(body) = Json::<TokenRequest>::from_request(request, &())
+            .await
+            .map_err(|err| {
+                TokenError::new(
+                    TokenErrorCode::InvalidRequest,
+                    &format!("Invalid JSON body: {}", err),
+                )
+            })?;
+        return Ok(body);
+    }
+    if content_type.starts_with("multipart/form-data") {
+        let multipart = Multipart::from_request(request, &())
+            .await
+            .map_err(|_| TokenError::new(TokenErrorCode::InvalidRequest, "Invalid form data"))?;
+        return parse_multipart(multipart).await;
+    }
+
+    Err(TokenError::new(
+        TokenErrorCode::InvalidRequest,
+        "Unsupported content type",
+    ))
+}
+
+/// Read the token request fields from multipart form data
+async fn parse_multipart(mut multipart: Multipart) -> Result<TokenRequest, TokenError> {
+    let invalid_form_data = || TokenError::new(TokenErrorCode::InvalidRequest, "Invalid form data");
+
+    let mut body = TokenRequest::default();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| invalid_form_data())?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        let value = field.text().await.map_err(|_| invalid_form_data())?;
+        match name.as_str() {
+            "grant_type" => body.grant_type = Some(value),
+            "scope" => body.scope = Some(value),
+            "client_id" => body.client_id = Some(value),
+            "client_secret" => body.client_secret = Some(value),
+            _ => {
+                // Auth should only contain the known fields, if anything else is found, return an error
+                debug!("Invalid field found: {:?}", name);
+                return Err(invalid_form_data());
+            }
+        }
+    }
+    Ok(body)
+}
+
+/// Authenticate the client of a token request against the client registry
+///
+/// The client can authenticate either with the Basic auth header or with `client_id` and `client_secret` in the
+/// request body, but not both.
+///
+/// # Parameters
+/// - `clients`: The registered clients by client ID
+/// - `headers`: The headers of the token request
+/// - `body`: The parsed token request body
+///
+/// # Returns
+/// - `Result<OAuthClient, TokenError>`: The authenticated client, or an error if the client credentials are invalid
+pub fn authenticate_client(
+    clients: &HashMap<String, OAuthClient>,
+    headers: &HeaderMap,
+    body: &TokenRequest,
+) -> Result<OAuthClient, TokenError> {
+    let invalid_credentials =
+        || TokenError::new(TokenErrorCode::InvalidClient, "Invalid credentials");
+
+    let (client_id, client_secret) = match (headers.get(AUTHORIZATION), &body.client_id) {
+        (Some(_), Some(_)) => {
+            debug!("Both auth header and body credentials found");
+            return Err(TokenError::new(
+                TokenErrorCode::InvalidRequest,
+                "Multiple client authentication methods",
+            ));
+        }
+        (Some(header), None) => header
+            .to_str()
+            .ok()
+            .and_then(decode_basic_auth_header)
+            .ok_or_else(invalid_credentials)?,
+        (None, Some(client_id)) => (
+            client_id.clone(),
+            body.client_secret.clone().unwrap_or_default(),
+        ),
+        (None, None) => {
+            debug!("No auth header or body credentials found");
+            return Err(TokenError::new(
+                TokenErrorCode::InvalidClient,
+                "No client credentials",
+            ));
+        }
+    };
+
+    match clients.get(&client_id) {
+        Some(client) if client.client_secret == client_secret => Ok(client.clone()),
+        _ => {
+            debug!("Invalid client credentials for client: {}", client_id);
+            Err(invalid_credentials())
+        }
+    }
+}
+
+/// Validate the grant type and scope of the token request
+///
+/// # Parameters
+/// - `body`: The parsed token request body
+/// - `client`: The authenticated client, the requested scopes have to be allowed for it
+///
+/// # Returns
+/// - `Result<String, TokenError>`: The requested scope, or an error if the grant type or scope is invalid
+pub fn validate_grant(body: &TokenRequest, client: &OAuthClient) -> Result<String, TokenError> {
+    let (grant_type, scope) = match (&body.grant_type, &body.scope) {
+        (Some(grant_type), Some(scope)) => (grant_type, scope),
+        _ => {
+            debug!("Missing grant_type or scope");
+            return Err(TokenError::new(
+                TokenErrorCode::InvalidRequest,
+                "Missing grant_type or scope",
+            ));
+        }
+    };
+    if grant_type != "client_credentials" {
+        debug!("Invalid grant_type: {}", grant_type);
+        return Err(TokenError::new(
+            TokenErrorCode::UnsupportedGrantType,
+            "Only the client_credentials grant type is supported",
+        ));
+    }
+    if scope.trim().is_empty() {
+        debug!("Empty scope");
+        return Err(TokenError::new(TokenErrorCode::InvalidScope, "Empty scope"));
+    }
+    if !client.allows_scope(scope) {
+        debug!(
+            "Scope {} not allowed for client {}",
+            scope, client.client_id
+        );
+        return Err(TokenError::new(
+            TokenErrorCode::InvalidScope,
+            "Scope not allowed for the client",
+        ));
+    }
+    debug!("Grant type: {}, scope: {}", grant_type, scope);
+    Ok(scope.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::oauth_clients::ClientRole;
+    use axum::body::Body;
+    use axum::http::HeaderValue;
+
+    fn request(content_type: &str, body: &str) -> Request {
+        Request::builder()
+            .method("POST")
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn client() -> OAuthClient {
+        OAuthClient {
+            client_id: "ven".to_string(),
+            client_secret: "secret".to_string(),
+            scopes: Some(vec!["read_all".to_string()]),
+            role: ClientRole::VEN,
+        }
+    }
+
+    fn expected_body() -> TokenRequest {
+        TokenRequest {
+            grant_type: Some("client_credentials".to_string()),
+            scope: Some("read_all".to_string()),
+            client_id: Some("ven".to_string()),
+            client_secret: Some("secret".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_token_request() {
+        let urlencoded = request(
+            "application/x-www-form-urlencoded",
+            "grant_type=client_credentials&scope=read_all&client_id=ven&client_secret=secret",
+        );
+        assert_eq!(parse_token_request(urlencoded).await, Ok(expected_body()));
+
+        let json = request(
+            "application/json",
+            r#"{"grant_type":"client_credentials","scope":"read_all","client_id":"ven","client_secret":"secret"}"#,
+        );
+        assert_eq!(parse_token_request(json).await, Ok(expected_body()));
+
+        let multipart = request(
+            "multipart/form-data; boundary=X",
+            "--X\r\nContent-Disposition: form-data; name=\"grant_type\"\r\n\r\nclient_credentials\r\n\
+             --X\r\nContent-Disposition: form-data; name=\"scope\"\r\n\r\nread_all\r\n\
+             --X\r\nContent-Disposition: form-data; name=\"client_id\"\r\n\r\nven\r\n\
+             --X\r\nContent-Disposition: form-data; name=\"client_secret\"\r\n\r\nsecret\r\n--X--\r\n",
+        );
+        assert_eq!(parse_token_request(multipart).await, Ok(expected_body()));
+
+        let unknown_field = request(
+            "application/x-www-form-urlencoded",
+            "grant_type=client_credentials&unknown=1",
+        );
+        assert!(parse_token_request(unknown_field).await.is_err());
+
+        let text = request("text/plain", "grant_type=client_credentials");
+        assert_eq!(
+            parse_token_request(text).await.unwrap_err().error,
+            TokenErrorCode::InvalidRequest
+        );
+    }
+
+    #[test]
+    fn test_validate_grant() {
+        assert_eq!(
+            validate_grant(&expected_body(), &client()),
+            Ok("read_all".to_string())
+        );
+
+        let error_code = |body: TokenRequest| validate_grant(&body, &client()).unwrap_err().error;
+        let password_grant = TokenRequest {
+            grant_type: Some("password".to_string()),
+            ..expected_body()
+        };
+        assert_eq!(
+            error_code(password_grant),
+            TokenErrorCode::UnsupportedGrantType
+        );
+        let empty_scope = TokenRequest {
+            scope: Some(" ".to_string()),
+            ..expected_body()
+        };
+        assert_eq!(error_code(empty_scope), TokenErrorCode::InvalidScope);
+        let not_allowed_scope = TokenRequest {
+            scope: Some("read_all write_events".to_string()),
+            ..expected_body()
+        };
+        assert_eq!(error_code(not_allowed_scope), TokenErrorCode::InvalidScope);
+        let missing_scope = TokenRequest {
+            scope: None,
+            ..expected_body()
+        };
+        assert_eq!(error_code(missing_scope), TokenErrorCode::InvalidRequest);
+    }
+
+    #[test]
+    fn test_token_error_response() {
+        let response =
+            TokenError::new(TokenErrorCode::InvalidClient, "Invalid credentials").into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(response.headers().contains_key(WWW_AUTHENTICATE));
+
+        let response = TokenError::new(TokenErrorCode::InvalidScope, "Empty scope").into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(!response.headers().contains_key(WWW_AUTHENTICATE));
+
+        let body = serde_json::to_value(TokenError::new(
+            TokenErrorCode::UnsupportedGrantType,
+            "Only client_credentials",
+        ))
+        .unwrap();
+        assert_eq!(body["error"], "unsupported_grant_type");
+        assert_eq!(body["error_description"], "Only client_credentials");
+    }
+
+    #[test]
+    fn test_authenticate_client() {
+        let clients = HashMap::from([("ven".to_string(), client())]);
+
+        // Basic auth header
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str("Basic dmVuOnNlY3JldA==").unwrap(),
+        );
+        assert_eq!(
+            authenticate_client(&clients, &headers, &TokenRequest::default()),
+            Ok(client())
+        );
+
+        // Body credentials
+        assert!(authenticate_client(&clients, &HeaderMap::new(), &expected_body()).is_ok());
+        let wrong_secret = TokenRequest {
+            client_secret: Some("wrong".to_string()),
+            ..expected_body()
+        };
+        assert!(authenticate_client(&clients, &HeaderMap::new(), &wrong_secret).is_err());
+
+        // Both or neither
+        assert_eq!(
+            authenticate_client(&clients, &headers, &expected_body())
+                .unwrap_err()
+                .error,
+            TokenErrorCode::InvalidRequest
+        );
+        assert_eq!(
+            authenticate_client(&clients, &HeaderMap::new(), &TokenRequest::default())
+                .unwrap_err()
+                .error,
+            TokenErrorCode::InvalidClient
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use crate::utils::oauth_clients::{ClientRole, OAuthClient};
+use crate::AppState;
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde::Serialize;
+use shuttle_runtime::SecretStore;
+
+/// Default lifetime of the issued access tokens in seconds
+const DEFAULT_TOKEN_LIFETIME_SECONDS: i64 = 3600;
+
+/// Client ID carried by the static token, which isn't issued to a specific client
+pub const STATIC_TOKEN_CLIENT_ID: &str = "static";
+
+/// Access token issued by the auth endpoint
+#[derive(Debug, Clone)]
+pub struct IssuedToken {
+    /// ID of the client the token was issued to
+    pub client_id: String,
+    /// Role of the client the token was issued to
+    pub role: ClientRole,
+    /// Expiry time of the token
+    pub expires_at: DateTime<Utc>,
+    /// Scope requested with the token
+    pub scope: Option<String>,
+}
+
+/// OAuth scopes defined by OpenADR 3.0, required by the OpenADR endpoints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    /// Read all objects
+    ReadAll,
+    /// Read the objects targeted to the client, also granted by read_all
+    ReadTargets,
+    /// Create, update and delete programs
+    WritePrograms,
+    /// Create, update and delete events
+    WriteEvents,
+    /// Create, update and delete reports
+    WriteReports,
+    /// Create, update and delete subscriptions
+    WriteSubscriptions,
+    /// Create, update and delete VENs and their resources
+    WriteVens,
+}
+
+impl Scope {
+    /// Name of the scope as requested from the auth endpoint
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ReadAll => "read_all",
+            Scope::ReadTargets => "read_targets",
+            Scope::WritePrograms => "write_programs",
+            Scope::WriteEvents => "write_events",
+            Scope::WriteReports => "write_reports",
+            Scope::WriteSubscriptions => "write_subscriptions",
+            Scope::WriteVens => "write_vens",
+        }
+    }
+}
+
+impl IssuedToken {
+    /// Check whether the token grants the scope
+    ///
+    /// Tokens without a scope, like the static token, grant every scope.
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        let granted = match &self.scope {
+            Some(granted) => granted,
+            None => return true,
+        };
+        granted.split_whitespace().any(|granted| {
+            granted == scope.as_str()
+                || (scope == Scope::ReadTargets && granted == Scope::ReadAll.as_str())
+        })
+    }
+}
+
+/// Read the lifetime of the issued access tokens from the optional `TOKEN_LIFETIME_SECONDS` secret
+///
+/// Invalid lifetimes fail at startup rather than when a token is requested.
+///
+/// # Parameters
+/// - `secrets`: The secrets store of the application
+///
+/// # Returns
+/// - `chrono::Duration`: The positive token lifetime, defaulting to an hour
+pub fn token_lifetime_from_secrets(secrets: &SecretStore) -> chrono::Duration {
+    let seconds = match secrets.get("TOKEN_LIFETIME_SECONDS") {
+        Some(value) => value
+            .parse::<i64>()
+            .ok()
+            .filter(|seconds| *seconds > 0)
+            .expect("TOKEN_LIFETIME_SECONDS must be a positive integer"),
+        None => DEFAULT_TOKEN_LIFETIME_SECONDS,
+    };
+    chrono::Duration::try_seconds(seconds).expect("TOKEN_LIFETIME_SECONDS is too large")
+}
+
+/// OAuth2 access token response
+#[derive(Debug, Serialize, Clone)]
+pub struct TokenResponse {
+    /// The access token to use as bearer token
+    pub access_token: String,
+    /// Token type, always Bearer
+    pub token_type: String,
+    /// Lifetime of the token in seconds
+    pub expires_in: i64,
+    /// Scope of the token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// Issue a new access token and store it in the token registry
+///
+/// Tokens are random and expire after the token lifetime of the application, see [`token_lifetime_from_secrets`].
+/// With the `STATIC_TOKEN = "true"` compatibility switch the static `DUMMY_TOKEN` secret is returned instead.
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `client`: The authenticated client the token is issued to
+/// - `scope`: The scope requested with the token
+///
+/// # Returns
+/// - `TokenResponse`: The OAuth2 token response
+pub fn issue_token(state: &AppState, client: &OAuthClient, scope: Option<String>) -> TokenResponse {
+    let lifetime = state.token_lifetime.num_seconds();
+
+    if static_token_enabled(state) {
+        return TokenResponse {
+            access_token: state
+                .secrets
+                .get("DUMMY_TOKEN")
+                .expect("DUMMY_TOKEN not set"),
+            token_type: "Bearer".to_string(),
+            expires_in: lifetime,
+            scope,
+        };
+    }
+
+    // Drop expired tokens so the registry doesn't grow indefinitely
+    let now = Utc::now();
+    state
+        .tokens
+        .retain(|_token, issued| issued.expires_at > now);
+
+    let access_token = uuid::Uuid::new_v4().simple().to_string();
+    state.tokens.insert(
+        access_token.clone(),
+        IssuedToken {
+            client_id: client.client_id.clone(),
+            role: client.role,
+            expires_at: now
+                .checked_add_signed(state.token_lifetime)
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            scope: scope.clone(),
+        },
+    );
+    debug!(
+        "Issued token to client {} expiring in {} seconds",
+        client.client_id, lifetime
+    );
+
+    TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: lifetime,
+        scope,
+    }
+}
+
+/// Check that a bearer token has been issued by the VTN and hasn't expired
+///
+/// With the `STATIC_TOKEN = "true"` compatibility switch, the static `DUMMY_TOKEN` secret is also valid. It isn't bound
+/// to a client, so it carries the `static` client ID with the VEN role and any scope.
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `token`: The bearer token
+///
+/// # Returns
+/// - `Option<IssuedToken>`: The issued token if valid, otherwise None
+pub fn validate_token(state: &AppState, token: &str) -> Option<IssuedToken> {
+    if static_token_enabled(state)
+        && state
+            .secrets
+            .get("DUMMY_TOKEN")
+            .is_some_and(|dummy_token| dummy_token == token)
+    {
+        return Some(IssuedToken {
+            client_id: STATIC_TOKEN_CLIENT_ID.to_string(),
+            role: ClientRole::VEN,
+            expires_at: DateTime::<Utc>::MAX_UTC,
+            scope: None,
+        });
+    }
+
+    let issued = state.tokens.get(token)?.clone();
+    if issued.expires_at <= Utc::now() {
+        debug!("Token expired at {}", issued.expires_at);
+        state.tokens.remove(token);
+        return None;
+    }
+    Some(issued)
+}
+
+/// Check whether the static token compatibility switch is enabled
+fn static_token_enabled(state: &AppState) -> bool {
+    state
+        .secrets
+        .get("STATIC_TOKEN")
+        .is_some_and(|value| value == "true")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shuttle_common::Secret;
+    use std::collections::BTreeMap;
+
+    fn secrets(lifetime: &str) -> SecretStore {
+        let mut secrets_tree: BTreeMap<String, Secret<String>> = BTreeMap::new();
+        secrets_tree.insert(
+            "TOKEN_LIFETIME_SECONDS".to_string(),
+            Secret::new(lifetime.to_string()),
+        );
+        SecretStore::new(secrets_tree)
+    }
+
+    #[test]
+    fn test_token_lifetime_from_secrets() {
+        assert_eq!(
+            token_lifetime_from_secrets(&SecretStore::new(BTreeMap::new())),
+            chrono::Duration::hours(1)
+        );
+        assert_eq!(
+            token_lifetime_from_secrets(&secrets("60")),
+            chrono::Duration::minutes(1)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "TOKEN_LIFETIME_SECONDS must be a positive integer")]
+    fn test_token_lifetime_not_positive() {
+        token_lifetime_from_secrets(&secrets("0"));
+    }
+
+    #[test]
+    #[should_panic(expected = "TOKEN_LIFETIME_SECONDS is too large")]
+    fn test_token_lifetime_too_large() {
+        token_lifetime_from_secrets(&secrets(&i64::MAX.to_string()));
+    }
+}
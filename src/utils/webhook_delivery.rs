@@ -0,0 +1,285 @@
+use crate::utils::iso8601::Timestamp;
+use crate::utils::openadr_models::ObjectOperation;
+use crate::AppState;
+use log::{debug, warn};
+use serde::Serialize;
+use shuttle_runtime::SecretStore;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Timeout and retry policy for the webhook deliveries to subscription callback URLs
+///
+/// Configured with the optional secrets `WEBHOOK_TIMEOUT_MS`, `WEBHOOK_MAX_ATTEMPTS`, `WEBHOOK_RETRY_BACKOFF_MS` and
+/// `WEBHOOK_LOG_LIMIT`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryPolicy {
+    /// Timeout of a single delivery attempt
+    pub timeout: Duration,
+    /// Maximum number of attempts per delivery, including the first one
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubled for every following retry
+    pub retry_backoff: Duration,
+    /// Maximum number of records kept in the delivery log, the oldest records are dropped first
+    pub log_limit: usize,
+}
+
+impl Default for DeliveryPolicy {
+    fn default() -> Self {
+        DeliveryPolicy {
+            timeout: Duration::from_millis(10_000),
+            max_attempts: 3,
+            retry_backoff: Duration::from_millis(500),
+            log_limit: 1000,
+        }
+    }
+}
+
+impl DeliveryPolicy {
+    /// Read the delivery policy from the secrets, using the defaults for missing values
+    ///
+    /// # Parameters
+    /// - `secrets`: The secrets store of the application
+    ///
+    /// # Returns
+    /// - `DeliveryPolicy`: The configured delivery policy
+    pub fn from_secrets(secrets: &SecretStore) -> DeliveryPolicy {
+        fn read<T: FromStr>(secrets: &SecretStore, key: &str) -> Option<T> {
+            secrets.get(key).map(|value| {
+                value
+                    .parse()
+                    .unwrap_or_else(|_| panic!("{} must be a positive integer", key))
+            })
+        }
+        let default = DeliveryPolicy::default();
+
+        DeliveryPolicy {
+            timeout: read(secrets, "WEBHOOK_TIMEOUT_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(default.timeout),
+            max_attempts: read(secrets, "WEBHOOK_MAX_ATTEMPTS")
+                .map(|attempts: u32| attempts.max(1))
+                .unwrap_or(default.max_attempts),
+            retry_backoff: read(secrets, "WEBHOOK_RETRY_BACKOFF_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(default.retry_backoff),
+            log_limit: read(secrets, "WEBHOOK_LOG_LIMIT").unwrap_or(default.log_limit),
+        }
+    }
+
+    /// Backoff to wait before the given retry, starting from 1. Saturates at `Duration::MAX` instead of overflowing.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        2u32.checked_pow(retry.saturating_sub(1))
+            .and_then(|factor| self.retry_backoff.checked_mul(factor))
+            .unwrap_or(Duration::MAX)
+    }
+}
+
+/// Record of a single webhook delivery, including all of its attempts
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryRecord {
+    /// Delivery ID
+    pub id: String,
+    /// ID of the subscription the delivery was made for
+    #[serde(rename = "subscriptionID")]
+    pub subscription_id: Option<String>,
+    /// Callback URL of the subscription object operation
+    pub callback_url: String,
+    /// The JSON body sent to the callback URL
+    pub payload: serde_json::Value,
+    /// Time of the first attempt
+    pub started_date_time: Timestamp,
+    /// Number of attempts made
+    pub attempts: u32,
+    /// True if the VEN responded with a success status code
+    pub success: bool,
+    /// HTTP status code of the last response
+    pub response_status: Option<u16>,
+    /// Body of the last response
+    pub response_body: Option<String>,
+    /// Latency of the last attempt in milliseconds
+    pub latency_ms: u64,
+    /// Transport error of the last attempt, eg. timeout or connection refused
+    pub error: Option<String>,
+}
+
+/// Deliver a JSON body to the callback URL of an object operation
+///
+/// The delivery is retried according to the delivery policy when the VEN can't be reached or responds with a
+/// server error. Client errors aren't retried. The delivery is recorded in the delivery log of the application, which
+/// keeps the most recent deliveries up to the log limit of the delivery policy.
+///
+/// # Parameters
+/// - `state`: The shared memory state of the application
+/// - `subscription_id`: The ID of the subscription the delivery is made for
+/// - `object_operation`: The object operation containing the callback URL and bearer token
+/// - `body`: The JSON body to send, see `notification_body`
+///
+/// # Returns
+/// - `DeliveryRecord`: The record of the delivery
+pub async fn deliver(
+    state: &AppState,
+    subscription_id: Option<String>,
+    object_operation: &ObjectOperation,
+    body: &serde_json::Value,
+) -> DeliveryRecord {
+    let policy = &state.delivery_policy;
+    let mut record = DeliveryRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        subscription_id,
+        callback_url: object_operation.callback_url.clone(),
+        payload: body.clone(),
+        started_date_time: Timestamp::now(),
+        attempts: 0,
+        success: false,
+        response_status: None,
+        response_body: None,
+        latency_ms: 0,
+        error: None,
+    };
+
+    while record.attempts < policy.max_attempts {
+        if record.attempts > 0 {
+            tokio::time::sleep(policy.backoff(record.attempts)).await;
+        }
+        record.attempts += 1;
+
+        let started = Instant::now();
+        let response = state
+            .http_client
+            .post(&object_operation.callback_url)
+            .timeout(policy.timeout)
+            .bearer_auth(&object_operation.bearer_token)
+            .json(body)
+            .send()
+            .await;
+
+        let retry = match response {
+            Ok(response) => {
+                let status = response.status();
+                record.response_status = Some(status.as_u16());
+                record.response_body = response.text().await.ok();
+                record.error = None;
+                record.success = status.is_success();
+                status.is_server_error()
+            }
+            Err(err) => {
+                record.response_status = None;
+                record.response_body = None;
+                record.error = Some(err.to_string());
+                true
+            }
+        };
+        record.latency_ms = started.elapsed().as_millis() as u64;
+
+        debug!(
+            "Delivery attempt {} to {}: status {:?}, error {:?}",
+            record.attempts, record.callback_url, record.response_status, record.error
+        );
+        if !retry {
+            break;
+        }
+    }
+
+    if !record.success {
+        warn!(
+            "Delivery to {} failed after {} attempts",
+            record.callback_url, record.attempts
+        );
+    }
+
+    let mut delivery_log = state.delivery_log.write().await;
+    delivery_log.push_back(record.clone());
+    while delivery_log.len() > policy.log_limit {
+        delivery_log.pop_front();
+    }
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::openadr_models::Operations;
+    use crate::utils::test_helpers::{test_state, UNREACHABLE_CALLBACK_URL};
+    use shuttle_common::Secret;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_delivery_policy() {
+        let secrets = SecretStore::new(BTreeMap::new());
+        assert_eq!(
+            DeliveryPolicy::from_secrets(&secrets),
+            DeliveryPolicy::default()
+        );
+
+        let mut secrets_tree: BTreeMap<String, Secret<String>> = BTreeMap::new();
+        secrets_tree.insert(
+            "WEBHOOK_TIMEOUT_MS".to_string(),
+            Secret::new("2000".to_string()),
+        );
+        secrets_tree.insert(
+            "WEBHOOK_MAX_ATTEMPTS".to_string(),
+            Secret::new("0".to_string()),
+        );
+        secrets_tree.insert(
+            "WEBHOOK_RETRY_BACKOFF_MS".to_string(),
+            Secret::new("100".to_string()),
+        );
+        let policy = DeliveryPolicy::from_secrets(&SecretStore::new(secrets_tree));
+
+        assert_eq!(policy.timeout, Duration::from_millis(2000));
+        assert_eq!(policy.max_attempts, 1);
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(40), Duration::MAX);
+        let policy = DeliveryPolicy {
+            retry_backoff: Duration::MAX,
+            ..policy
+        };
+        assert_eq!(policy.backoff(2), Duration::MAX);
+        assert_eq!(policy.log_limit, DeliveryPolicy::default().log_limit);
+    }
+
+    #[test]
+    #[should_panic(expected = "WEBHOOK_MAX_ATTEMPTS must be a positive integer")]
+    fn test_delivery_policy_attempts_overflow() {
+        let mut secrets_tree: BTreeMap<String, Secret<String>> = BTreeMap::new();
+        secrets_tree.insert(
+            "WEBHOOK_MAX_ATTEMPTS".to_string(),
+            Secret::new("4294967296".to_string()),
+        );
+        DeliveryPolicy::from_secrets(&SecretStore::new(secrets_tree));
+    }
+
+    #[tokio::test]
+    async fn test_delivery_log_limit() {
+        let state = test_state(&[
+            ("WEBHOOK_TIMEOUT_MS", "1000"),
+            ("WEBHOOK_MAX_ATTEMPTS", "1"),
+            ("WEBHOOK_LOG_LIMIT", "2"),
+        ])
+        .await;
+        let object_operation = ObjectOperation {
+            object_type: vec![],
+            operations: Operations { operations: vec![] },
+            callback_url: UNREACHABLE_CALLBACK_URL.to_string(),
+            bearer_token: "token".to_string(),
+        };
+
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let record = deliver(&state, None, &object_operation, &serde_json::json!({})).await;
+            assert!(!record.success);
+            // Timestamps are serialized like the other timestamps of the VTN
+            let started = serde_json::to_value(&record).unwrap()["startedDateTime"].clone();
+            assert!(started.as_str().unwrap().ends_with('Z'));
+            ids.push(record.id);
+        }
+
+        // Only the most recent deliveries are kept
+        let delivery_log = state.delivery_log.read().await;
+        let logged: Vec<&String> = delivery_log.iter().map(|record| &record.id).collect();
+        assert_eq!(logged, vec![&ids[1], &ids[2]]);
+    }
+}